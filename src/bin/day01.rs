@@ -1,10 +1,9 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
-use std::str::Lines;
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
 
 struct Solver;
 
 impl Solver {
-    fn solve_for_window(&self, input: Lines, window_sz: usize) -> usize {
+    fn solve_for_window(&self, input: Input, window_sz: usize) -> usize {
         let depths: Vec<i32> = input.map(|line| line.parse().unwrap()).collect();
 
         depths
@@ -17,11 +16,11 @@ impl Solver {
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         self.solve_for_window(input, 1)
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
         self.solve_for_window(input, 3)
     }
 }
@@ -60,10 +59,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 7);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 5);
     }
 }
@@ -1,5 +1,5 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
-use std::str::{FromStr, Lines};
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
+use std::str::FromStr;
 
 enum Instruction {
     Fwd(i32),
@@ -28,7 +28,7 @@ struct Solver;
 impl Solution for Solver {
     type Output = i32;
 
-    fn solve_a(&self, input: Lines) -> i32 {
+    fn solve_a(&self, input: Input) -> i32 {
         let mut pos = 0;
         let mut depth = 0;
         for line in input {
@@ -48,7 +48,7 @@ impl Solution for Solver {
         pos * depth
     }
 
-    fn solve_b(&self, input: Lines) -> i32 {
+    fn solve_b(&self, input: Input) -> i32 {
         let mut pos = 0;
         let mut depth = 0;
         let mut aim = 0;
@@ -101,10 +101,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 150);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 900);
     }
 }
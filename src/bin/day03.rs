@@ -1,38 +1,30 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
-use std::str::Lines;
+use adventofcode::{self as aoc, aoc_problem, Counter, Input, Solution};
 
 struct Solver;
 
 impl Solution for Solver {
     type Output = u32;
 
-    fn solve_a(&self, input: Lines) -> u32 {
+    fn solve_a(&self, input: Input) -> u32 {
         let nums: Vec<_> = input
             .map(|line| (line.len(), u32::from_str_radix(line, 2).unwrap()))
             .collect();
         let sz = nums[0].0;
 
+        let ones: Counter<usize> = nums
+            .iter()
+            .flat_map(|&(_, n)| (0..sz).filter(move |&shift| n & (1_u32 << shift) > 0))
+            .collect();
+
         let gamma: u32 = (0..sz)
+            .filter(|shift| ones[shift] >= nums.len() / 2)
             .map(|shift| 1_u32 << shift)
-            .map(|pow| {
-                nums.iter().filter(|&&(_, n)| n & pow > 0).count() >= nums.len() / 2
-            })
-            .zip(0..sz)
-            .map(
-                |(bit_non_empty, y)| {
-                    if bit_non_empty {
-                        1_u32 << y as u32
-                    } else {
-                        0
-                    }
-                },
-            )
             .sum();
 
         gamma * ((1_u32 << sz) - gamma - 1)
     }
 
-    fn solve_b(&self, input: Lines) -> u32 {
+    fn solve_b(&self, input: Input) -> u32 {
         let nums: Vec<_> = input
             .map(|line| (line.len(), u32::from_str_radix(line, 2).unwrap()))
             .collect();
@@ -104,10 +96,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 198);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 230);
     }
 }
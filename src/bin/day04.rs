@@ -1,7 +1,6 @@
-use adventofcode::{self as aoc, aoc_problem, map, Solution};
+use adventofcode::{self as aoc, aoc_problem, map, Input, Solution};
 use itertools::Itertools;
 use std::collections::HashMap;
-use std::str::Lines;
 
 #[derive(Debug)]
 struct Board {
@@ -61,31 +60,19 @@ impl Board {
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, mut input: Lines) -> (Vec<u32>, Vec<Board>) {
-        let sequence: Vec<u32> = input
+    fn parse_input(&self, input: Input) -> (Vec<u32>, Vec<Board>) {
+        let mut sections = input.sections();
+
+        let sequence = sections
             .next()
-            .unwrap()
-            .split(',')
-            .map(|n| n.parse().unwrap())
-            .collect();
+            .expect("Expect drawn numbers")
+            .numbers()
+            .unwrap();
 
-        let mut boards = vec![];
-
-        while let Some(_) = input.next() {
-            let rows: Vec<u32> = [
-                input.next().unwrap(),
-                input.next().unwrap(),
-                input.next().unwrap(),
-                input.next().unwrap(),
-                input.next().unwrap(),
-            ]
-            .iter()
-            .flat_map(|line| line.split_whitespace().map(|n| n.parse().unwrap()))
+        let boards = sections
+            .map(|board| Board::new(&board.numbers().unwrap()))
             .collect();
 
-            boards.push(Board::new(&rows));
-        }
-
         (sequence, boards)
     }
 }
@@ -93,7 +80,7 @@ impl Solver {
 impl Solution for Solver {
     type Output = u32;
 
-    fn solve_a(&self, input: Lines) -> u32 {
+    fn solve_a(&self, input: Input) -> u32 {
         let (sequence, mut boards) = self.parse_input(input);
 
         for drawn in sequence {
@@ -106,7 +93,7 @@ impl Solution for Solver {
         unreachable!("Solution should exist");
     }
 
-    fn solve_b(&self, input: Lines) -> u32 {
+    fn solve_b(&self, input: Input) -> u32 {
         let (sequence, mut boards) = self.parse_input(input);
 
         let mut last_score = 0;
@@ -165,10 +152,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 4512);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 1924);
     }
 }
@@ -1,6 +1,6 @@
-use adventofcode::{self as aoc, aoc_problem, make_err, map, Solution};
+use adventofcode::{self as aoc, aoc_problem, make_err, map, Input, Solution};
 use std::cmp::Ordering;
-use std::str::{FromStr, Lines};
+use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 struct Point {
@@ -94,7 +94,7 @@ struct Solver;
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         let segments: Vec<Segment> = input
             .map(|line| line.parse().unwrap())
             .filter(|s: &Segment| s.0.x == s.1.x || s.0.y == s.1.y)
@@ -103,7 +103,7 @@ impl Solution for Solver {
         count_intersections(&segments)
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
         let segments: Vec<Segment> = input.map(|line| line.parse().unwrap()).collect();
 
         count_intersections(&segments)
@@ -144,10 +144,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 5);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 12);
     }
 }
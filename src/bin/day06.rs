@@ -1,33 +1,49 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
-use std::str::Lines;
+use adventofcode::util::linalg::{self, Matrix};
+use adventofcode::{self as aoc, aoc_problem, make_err, Counter, Input, Solution};
 
-struct Solver;
+/// Returns the transition matrix for the numbers of fish with each timer value.
+fn lanternfish_matrix() -> Matrix {
+    let mut m = Matrix::zeros(9, 9);
+    for t in 1..9 {
+        m[(t - 1, t)] = 1;
+    }
+    m[(6, 0)] = 1;
+    m[(8, 0)] = 1;
+    m
+}
 
-impl Solver {
-    fn count_fish(&self, input: Lines, days: usize) -> usize {
-        let mut timers = [0; 9];
+/// Counts the fish after any number of days, or returns `Err` if the count
+/// doesn't fit into `u64`.
+fn count_fish(timers: &[u64], days: u64) -> Result<u64, aoc::Error> {
+    let timers = lanternfish_matrix()
+        .checked_pow(days)?
+        .checked_mul_vec(timers)?;
+    linalg::checked_sum(&timers)
+}
 
-        input
-            .flat_map(|line| line.split(',').map(|n| n.parse::<usize>().unwrap()))
-            .for_each(|t| timers[t] += 1);
+struct Solver;
 
-        for d in 0..days {
-            timers[(d + 7) % timers.len()] += timers[d % timers.len()];
+impl Solver {
+    fn parse_input(&self, input: Input) -> Result<Vec<u64>, aoc::Error> {
+        let timers: Vec<usize> = input.numbers()?;
+        if let Some(t) = timers.iter().find(|&&t| t > 8) {
+            return make_err!("Timer {} is out of range, at most 8 expected", t);
         }
 
-        timers.iter().sum()
+        let fish: Counter<usize> = timers.into_iter().collect();
+        Ok((0..9).map(|t| fish[&t] as u64).collect())
     }
 }
 
 impl Solution for Solver {
-    type Output = usize;
+    type Output = u64;
 
-    fn solve_a(&self, input: Lines) -> usize {
-        self.count_fish(input, 80)
+    fn solve_a(&self, input: Input) -> u64 {
+        count_fish(&self.parse_input(input).unwrap(), 80).unwrap()
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
-        self.count_fish(input, 256)
+    fn solve_b(&self, input: Input) -> u64 {
+        count_fish(&self.parse_input(input).unwrap(), 256).unwrap()
     }
 }
 
@@ -45,6 +61,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use adventofcode::util::linalg::ModMatrix;
     use aoc::StripMargin;
 
     #[test]
@@ -56,10 +73,33 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 5_934);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 26_984_457_539);
     }
+
+    #[test]
+    fn test_far_future() {
+        let solver = Solver {};
+        let timers = solver.parse_input(Input::new("3,4,3,1,2")).unwrap();
+
+        assert!(count_fish(&timers, 1_000_000_000_000_000).is_err());
+
+        let m = ModMatrix::new(lanternfish_matrix(), 1_000_000_007);
+        let timers = m.pow(1_000_000_000_000_000).mul_vec(&timers);
+        let ans = timers.iter().sum::<u64>() % m.modulus();
+        assert_eq!(ans, 3_308_113);
+    }
+
+    #[test]
+    fn test_invalid_timers() {
+        let solver = Solver {};
+        let err = solver.parse_input(Input::new("3,4,9,1,2")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Timer 9 is out of range, at most 8 expected"
+        );
+    }
 }
@@ -1,5 +1,4 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
-use std::str::Lines;
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
 
 struct Solver;
 
@@ -23,7 +22,7 @@ impl Solver {
 impl Solution for Solver {
     type Output = i32;
 
-    fn solve_a(&self, input: Lines) -> i32 {
+    fn solve_a(&self, input: Input) -> i32 {
         let positions: Vec<i32> = input
             .flat_map(|line| line.split(',').map(|n| n.parse().unwrap()))
             .collect();
@@ -31,7 +30,7 @@ impl Solution for Solver {
         self.align_crabs(positions, |x1, x2| (x1 - x2).abs())
     }
 
-    fn solve_b(&self, input: Lines) -> i32 {
+    fn solve_b(&self, input: Input) -> i32 {
         let positions: Vec<i32> = input
             .flat_map(|line| line.split(',').map(|n| n.parse().unwrap()))
             .collect();
@@ -68,10 +67,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 37);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 168);
     }
 }
@@ -1,84 +1,171 @@
-use adventofcode::{self as aoc, aoc_problem, map, set, Solution};
+use adventofcode::{self as aoc, aoc_problem, make_err, set, Input, Solution};
 use itertools::Itertools;
 use std::collections::HashSet;
-use std::str::Lines;
 
-trait SortedString {
-    fn as_sorted_string(&self) -> String;
+/// Digits of a segment display, along with the segments that are lit for each of them.
+///
+/// Segments are named `a`, `b`, `c`, ..., and stored as bits of a mask.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Font {
+    digits: Vec<(char, u32)>,
+    segments: usize,
 }
 
-impl SortedString for HashSet<char> {
-    fn as_sorted_string(&self) -> String {
-        self.iter().sorted().collect()
+/// Mapping of every wire of a display to the segment it's connected to (as a bit).
+type Wiring = Vec<u32>;
+
+fn parse_pattern(pattern: &str, segments: usize) -> Result<u32, aoc::Error> {
+    let mut mask = 0;
+    for c in pattern.chars() {
+        let bit = match (c as u32).checked_sub('a' as u32) {
+            Some(idx) if (idx as usize) < segments => 1 << idx,
+            _ => return make_err!("Unexpected segment `{}` in `{}`", c, pattern),
+        };
+        if mask & bit != 0 {
+            return make_err!("Segment `{}` repeats in `{}`", c, pattern);
+        }
+        mask |= bit;
     }
+    Ok(mask)
 }
 
-impl SortedString for &str {
-    fn as_sorted_string(&self) -> String {
-        self.chars().sorted().collect()
+impl Font {
+    /// Creates the font from the segments of every digit, like `('1', "cf")`.
+    fn new(digits: &[(char, &str)]) -> Result<Self, aoc::Error> {
+        let chars = || digits.iter().flat_map(|(_, pattern)| pattern.chars());
+        if let Some(c) = chars().find(|c| !c.is_ascii_lowercase()) {
+            return make_err!("Unexpected segment `{}`, expected a letter a to z", c);
+        }
+        let segments = chars().map(|c| c as usize - 'a' as usize + 1).max();
+
+        let mut font = Font {
+            digits: vec![],
+            segments: segments.unwrap_or(0),
+        };
+        for &(digit, pattern) in digits {
+            let mask = parse_pattern(pattern, font.segments)?;
+            if let Some(&(other, _)) = font.digits.iter().find(|d| d.1 == mask) {
+                return make_err!(
+                    "Digits {} and {} are both shown as `{}`",
+                    other,
+                    digit,
+                    pattern
+                );
+            }
+            font.digits.push((digit, mask));
+        }
+        Ok(font)
     }
-}
 
-struct Solver;
+    /// Decimal digits on a seven-segment display.
+    fn decimal() -> Self {
+        Font::new(&DECIMAL).unwrap()
+    }
+
+    /// Finds every wiring of the display that turns all of the patterns into digits.
+    fn wirings(&self, patterns: &[u32]) -> Vec<Wiring> {
+        let mut found = vec![];
+        let mut wiring = vec![];
+        self.search(patterns, &mut wiring, &mut found);
+        found
+    }
+
+    fn search(&self, patterns: &[u32], wiring: &mut Wiring, found: &mut Vec<Wiring>) {
+        let wired: u32 = wiring.iter().sum();
+        let consistent = patterns.iter().all(|&pattern| {
+            let image = self.image(pattern, wiring);
+            self.digits.iter().any(|&(_, digit)| {
+                digit.count_ones() == pattern.count_ones() && digit & wired == image
+            })
+        });
+        if !consistent {
+            return;
+        }
+        if wiring.len() == self.segments {
+            found.push(wiring.clone());
+            return;
+        }
+
+        for segment in (0..self.segments).map(|s| 1 << s) {
+            if wired & segment == 0 {
+                wiring.push(segment);
+                self.search(patterns, wiring, found);
+                wiring.pop();
+            }
+        }
+    }
+
+    /// Returns the segments lit by the (wired part of the) pattern.
+    fn image(&self, pattern: u32, wiring: &[u32]) -> u32 {
+        wiring
+            .iter()
+            .enumerate()
+            .filter(|&(wire, _)| pattern & (1 << wire) != 0)
+            .map(|(_, segment)| segment)
+            .sum()
+    }
+
+    /// Reads the output of the display, after figuring out its wiring
+    /// from the signal patterns and the output itself.
+    fn decode(&self, patterns: &[&str], output: &[&str]) -> Result<String, aoc::Error> {
+        let output = output
+            .iter()
+            .map(|p| parse_pattern(p, self.segments))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut patterns = patterns
+            .iter()
+            .map(|p| parse_pattern(p, self.segments))
+            .collect::<Result<Vec<_>, _>>()?;
+        patterns.extend(&output);
+        patterns.sort_unstable();
+        patterns.dedup();
+
+        let readings: Vec<String> = self
+            .wirings(&patterns)
+            .iter()
+            .map(|wiring| {
+                output
+                    .iter()
+                    .map(|&pattern| {
+                        let image = self.image(pattern, wiring);
+                        self.digits.iter().find(|d| d.1 == image).unwrap().0
+                    })
+                    .collect()
+            })
+            .sorted()
+            .dedup()
+            .collect();
 
-fn decode(inputs: Vec<String>, outputs: Vec<String>) -> usize {
-    let input_sets: Vec<HashSet<_>> =
-        inputs.iter().map(|el| el.chars().collect()).collect();
-
-    let d_1 = input_sets.iter().find(|el| el.len() == 2).unwrap();
-    let d_4 = input_sets.iter().find(|el| el.len() == 4).unwrap();
-    let d_7 = input_sets.iter().find(|el| el.len() == 3).unwrap();
-    let d_8 = input_sets.iter().find(|el| el.len() == 7).unwrap();
-    let d_3 = input_sets
-        .iter()
-        .find(|el| el.len() == 5 && el.is_superset(d_1))
-        .unwrap();
-    let d_6 = input_sets
-        .iter()
-        .find(|el| el.len() == 6 && !el.is_superset(d_1))
-        .unwrap();
-    let d_9 = input_sets
-        .iter()
-        .find(|el| el.len() == 6 && el.is_superset(d_3))
-        .unwrap();
-    let d_0 = input_sets
-        .iter()
-        .find(|el| el.len() == 6 && el != &d_6 && el != &d_9)
-        .unwrap();
-    let d_5 = input_sets
-        .iter()
-        .find(|el| el.len() == 5 && el != &d_3 && el.is_subset(d_9))
-        .unwrap();
-    let d_2 = input_sets
-        .iter()
-        .find(|el| el.len() == 5 && el != &d_3 && el != &d_5)
-        .unwrap();
-
-    let mapping = map![
-        d_0.as_sorted_string() => 0,
-        d_1.as_sorted_string() => 1,
-        d_2.as_sorted_string() => 2,
-        d_3.as_sorted_string() => 3,
-        d_4.as_sorted_string() => 4,
-        d_5.as_sorted_string() => 5,
-        d_6.as_sorted_string() => 6,
-        d_7.as_sorted_string() => 7,
-        d_8.as_sorted_string() => 8,
-        d_9.as_sorted_string() => 9,
-    ];
-
-    outputs
-        .iter()
-        .map(|el| mapping.get(el).unwrap())
-        .zip([1000, 100, 10, 1])
-        .map(|(&digit, power)| digit * power)
-        .sum()
+        match readings.len() {
+            0 => make_err!("Signal patterns are inconsistent with the digits"),
+            1 => Ok(readings.into_iter().next().unwrap()),
+            _ => make_err!(
+                "Signal patterns are ambiguous, the output can be any of {}",
+                readings.join(", ")
+            ),
+        }
+    }
 }
 
+const DECIMAL: [(char, &str); 10] = [
+    ('0', "abcefg"),
+    ('1', "cf"),
+    ('2', "acdeg"),
+    ('3', "acdfg"),
+    ('4', "bcdf"),
+    ('5', "abdfg"),
+    ('6', "abdefg"),
+    ('7', "acf"),
+    ('8', "abcdefg"),
+    ('9', "abcdfg"),
+];
+
+struct Solver;
+
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         let output: Vec<_> = input
             .flat_map(|line| line.split_once('|').unwrap().1.split_whitespace())
             .collect();
@@ -87,20 +174,18 @@ impl Solution for Solver {
         output.iter().filter(|el| sizes.contains(&el.len())).count()
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
+        let font = Font::decimal();
         input
             .map(|line| line.split_once('|').unwrap())
             .map(|(left, right)| {
-                (
-                    left.trim().split_whitespace().map(str::to_string).collect(),
-                    right
-                        .trim()
-                        .split_whitespace()
-                        .map(|s| s.as_sorted_string())
-                        .collect(),
-                )
+                let patterns: Vec<_> = left.split_whitespace().collect();
+                let output: Vec<_> = right.split_whitespace().collect();
+                match font.decode(&patterns, &output) {
+                    Ok(value) => value.parse::<usize>().unwrap(),
+                    Err(err) => panic!("{} in {}", err, right),
+                }
             })
-            .map(|(left, right)| decode(left, right))
             .sum()
     }
 }
@@ -139,10 +224,105 @@ mod tests {
         .trim()
         .strip_margin_of("> ");
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 26);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 61229);
     }
+
+    /// Connects the segments of the font to the wires in the given order,
+    /// and returns the pattern that shows every digit of `value`.
+    fn scramble(font: &Font, wires: &str, value: &str) -> Vec<String> {
+        let wires: Vec<char> = wires.chars().collect();
+        value
+            .chars()
+            .map(|c| {
+                let (_, mask) = font.digits.iter().find(|d| d.0 == c).unwrap();
+                (0..font.segments)
+                    .filter(|s| mask & (1 << s) != 0)
+                    .map(|s| wires[s])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode() {
+        let font = Font::decimal();
+        let (left, right) =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab \
+            | cdfeb fcadb cdfeb cdbaf"
+                .split_once(" | ")
+                .unwrap();
+        let patterns: Vec<_> = left.split_whitespace().collect();
+        let output: Vec<_> = right.split_whitespace().collect();
+        assert_eq!(font.decode(&patterns, &output).unwrap(), "5353");
+
+        // the output is readable even from a few of the patterns
+        assert_eq!(font.decode(&patterns[4..8], &output).unwrap(), "5353");
+        assert_eq!(font.decode(&["ab"], &["ab", "ba"]).unwrap(), "11");
+        assert_eq!(font.decode(&[], &["eafb", "acedgfb"]).unwrap(), "48");
+
+        assert_eq!(
+            font.decode(&[], &["cdfbe"]).unwrap_err().to_string(),
+            "Error: Signal patterns are ambiguous, the output can be any of 2, 3, 5"
+        );
+        assert_eq!(
+            font.decode(&["ab", "abc"], &["bcd"])
+                .unwrap_err()
+                .to_string(),
+            "Error: Signal patterns are inconsistent with the digits"
+        );
+        assert_eq!(
+            font.decode(&["abh"], &[]).unwrap_err().to_string(),
+            "Error: Unexpected segment `h` in `abh`"
+        );
+        assert_eq!(
+            font.decode(&["aba"], &[]).unwrap_err().to_string(),
+            "Error: Segment `a` repeats in `aba`"
+        );
+    }
+
+    #[test]
+    fn test_custom_fonts() {
+        let mut hex = DECIMAL.to_vec();
+        hex.extend([
+            ('A', "abcdef"),
+            ('b', "bdefg"),
+            ('C', "abeg"),
+            ('d', "cdefg"),
+            ('E', "abdeg"),
+            ('F', "abde"),
+        ]);
+        let hex = Font::new(&hex).unwrap();
+
+        let wires = "dgaecbf";
+        let patterns = scramble(&hex, wires, "0123456789AbCdEF");
+        let patterns: Vec<_> = patterns.iter().map(String::as_str).collect();
+        let output = scramble(&hex, wires, "C0FFEE");
+        let output: Vec<_> = output.iter().map(String::as_str).collect();
+        assert_eq!(hex.decode(&patterns, &output).unwrap(), "C0FFEE");
+
+        // a font with fewer segments: the tally marks from one to four
+        let tally =
+            Font::new(&[('1', "a"), ('2', "ab"), ('3', "abc"), ('4', "abcd")]).unwrap();
+        assert_eq!(tally.segments, 4);
+        assert_eq!(tally.decode(&[], &["cb", "dacb"]).unwrap(), "24");
+
+        for (digits, err) in [
+            (
+                vec![('0', "abc"), ('1', "cab")],
+                "Digits 0 and 1 are both shown as `cab`",
+            ),
+            (
+                vec![('0', "abC")],
+                "Unexpected segment `C`, expected a letter a to z",
+            ),
+            (vec![('0', "abb")], "Segment `b` repeats in `abb`"),
+        ] {
+            let actual = Font::new(&digits).unwrap_err();
+            assert_eq!(actual.to_string(), format!("Error: {}", err));
+        }
+    }
 }
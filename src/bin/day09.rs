@@ -1,7 +1,6 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
 use itertools::Itertools;
 use std::iter;
-use std::str::Lines;
 
 struct Solver;
 
@@ -50,7 +49,7 @@ fn dfs(
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         let heights: Vec<Vec<_>> = input.map(|el| el.chars().collect()).collect();
         let mut risk = 0;
 
@@ -69,7 +68,7 @@ impl Solution for Solver {
         risk
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
         let mut heights: Vec<Vec<_>> = input
             .map(|el| el.chars().zip(iter::repeat(0)).collect())
             .collect();
@@ -127,10 +126,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 15);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 1134);
     }
 }
@@ -1,80 +1,42 @@
-use adventofcode::{self as aoc, aoc_problem, map, Solution};
+use adventofcode::util::brackets::{Brackets, Corruption, Status};
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
 use itertools::Itertools;
-use lazy_static::lazy_static;
-use std::collections::{HashMap, VecDeque};
-use std::str::Lines;
-
-lazy_static! {
-    static ref PAIRS: HashMap<char, char> = map![
-        '(' => ')',
-        '[' => ']',
-        '{' => '}',
-        '<' => '>',
-    ];
-    static ref SCORES_1: HashMap<char, usize> = map![
-        ')' => 3,
-        ']' => 57,
-        '}' => 1197,
-        '>' => 25137,
-    ];
-    static ref SCORES_2: HashMap<char, usize> = map![
-        ')' => 1,
-        ']' => 2,
-        '}' => 3,
-        '>' => 4,
-    ];
+use std::collections::HashMap;
+
+/// Chunks of the navigation subsystem: the opening and closing characters,
+/// and the scores of the closing character for corrupted and incomplete lines.
+const CHUNKS: [(char, char, usize, usize); 4] = [
+    ('(', ')', 3, 1),
+    ('[', ']', 57, 2),
+    ('{', '}', 1197, 3),
+    ('<', '>', 25137, 4),
+];
+
+/// Checker of the lines, along with the scores of the errors.
+struct Checker {
+    brackets: Brackets,
+    corrupted: HashMap<char, usize>,
+    completion: HashMap<char, usize>,
 }
 
-enum Type {
-    Corrupted(char),
-    Incomplete(String),
-    Good,
-}
-
-impl Type {
-    fn corrupted(self) -> Option<char> {
-        match self {
-            Type::Corrupted(c) => Some(c),
-            _ => None,
-        }
+impl Checker {
+    fn new(chunks: &[(char, char, usize, usize)]) -> Result<Self, aoc::Error> {
+        let pairs: Vec<_> = chunks.iter().map(|&(o, c, _, _)| (o, c)).collect();
+        Ok(Self {
+            brackets: Brackets::new(&pairs)?,
+            corrupted: chunks.iter().map(|&(_, c, s, _)| (c, s)).collect(),
+            completion: chunks.iter().map(|&(_, c, _, s)| (c, s)).collect(),
+        })
     }
-    fn incomplete(self) -> Option<String> {
-        match self {
-            Type::Incomplete(s) => Some(s),
-            _ => None,
-        }
-    }
-}
 
-trait Analyze {
-    fn analyze(&self) -> Type;
-}
+    fn corrupted_score(&self, corruption: &Corruption) -> usize {
+        self.corrupted[&corruption.found]
+    }
 
-impl Analyze for &str {
-    fn analyze(&self) -> Type {
-        let mut deque = VecDeque::new();
-        for c in self.chars() {
-            match c {
-                '(' | '[' | '{' | '<' => {
-                    deque.push_back(c);
-                }
-                ')' | ']' | '}' | '>' => match deque.pop_back().unwrap() {
-                    b if PAIRS.get(&b) != Some(&c) => {
-                        return Type::Corrupted(c);
-                    }
-                    _ => {}
-                },
-                _ => {}
-            }
-        }
-
-        if deque.is_empty() {
-            return Type::Good;
-        }
-
-        let remaining: String = deque.iter().map(|c| PAIRS.get(c).unwrap()).collect();
-
-        Type::Incomplete(remaining)
+    fn completion_score(&self, completion: &str) -> usize {
+        completion
+            .chars()
+            .fold(0, |score, c| 5 * score + self.completion[&c])
     }
 }
 
@@ -83,21 +45,22 @@ struct Solver;
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
+        let checker = Checker::new(&CHUNKS).unwrap();
         input
-            .flat_map(|line| line.analyze().corrupted())
-            .map(|c| SCORES_1.get(&c).copied().unwrap())
+            .map(|line| match checker.brackets.check(line) {
+                Status::Corrupted(corruption) => checker.corrupted_score(&corruption),
+                _ => 0,
+            })
             .sum()
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
+        let checker = Checker::new(&CHUNKS).unwrap();
         let scores: Vec<_> = input
-            .flat_map(|line| line.analyze().incomplete())
-            .map(|el| {
-                el.chars()
-                    .zip(0..)
-                    .map(|(c, pw)| SCORES_2.get(&c).unwrap() * 5_usize.pow(pw))
-                    .sum()
+            .flat_map(|line| match checker.brackets.check(line) {
+                Status::Incomplete(rest) => Some(checker.completion_score(&rest)),
+                _ => None,
             })
             .sorted()
             .collect();
@@ -140,10 +103,63 @@ mod tests {
         .trim()
         .strip_margin_of("- ");
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 26397);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 288957);
+
+        let checker = Checker::new(&CHUNKS).unwrap();
+        let report: Vec<_> = Input::new(&input)
+            .map(|line| match checker.brackets.check(line) {
+                Status::Corrupted(corruption) => {
+                    let fix = checker.brackets.repair(line).unwrap();
+                    format!("{}; {}", corruption, fix)
+                }
+                status => status.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                "incomplete, complete by adding `}}]])})]`",
+                "incomplete, complete by adding `)}>]})`",
+                "column 13: expected `]`, found `}`; replace `}` with `]` at column 13",
+                "incomplete, complete by adding `}}>}>))))`",
+                "column 9: expected `]`, found `)`; replace `)` with `]` at column 9",
+                "column 8: expected `)`, found `]`; replace `]` with `)` at column 8",
+                "incomplete, complete by adding `]]}}]}]}>`",
+                "column 11: expected `>`, found `)`; replace `)` with `>` at column 11",
+                "column 17: expected `]`, found `>`; replace `>` with `]` at column 17",
+                "incomplete, complete by adding `])}>`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_language() {
+        // XML-like tags, where the sloppy closing tags cost more
+        let chunks = [('a', 'A', 10, 1), ('b', 'B', 20, 2), ('|', '!', 5, 3)];
+        let checker = Checker::new(&chunks).unwrap();
+
+        let status = checker.brackets.check("ab|!BA b|B");
+        let corruption = match status {
+            Status::Corrupted(corruption) => corruption,
+            status => panic!("Unexpected {:?}", status),
+        };
+        assert_eq!(corruption.to_string(), "column 10: expected `!`, found `B`");
+        assert_eq!(checker.corrupted_score(&corruption), 20);
+
+        assert_eq!(
+            checker.brackets.check("a|b"),
+            Status::Incomplete("B!A".to_string())
+        );
+        assert_eq!(checker.completion_score("B!A"), (2 * 5 + 3) * 5 + 1);
+
+        let chunks = [('(', ')', 1, 1), ('[', '(', 2, 2)];
+        assert_eq!(
+            Checker::new(&chunks).err().unwrap().to_string(),
+            "Error: `(` is used by more than one pair"
+        );
     }
 }
@@ -1,8 +1,8 @@
-use adventofcode::{self as aoc, aoc_problem, set, Solution};
+use adventofcode::{
+    self as aoc, aoc_problem, set, util::simulation::Simulation, Input, Solution,
+};
 use itertools::Itertools;
 use std::collections::HashMap;
-use std::iter;
-use std::str::Lines;
 
 const N: usize = 10;
 
@@ -63,34 +63,59 @@ fn run_step(octopuses: &mut HashMap<(usize, usize), Octopus>) -> usize {
     octopuses.values().filter(|oct| oct.flashed).count()
 }
 
+/// Octopuses in the cavern, along with the number of flashes so far.
+struct Cavern {
+    octopuses: HashMap<(usize, usize), Octopus>,
+    last_flashes: usize,
+    total_flashes: usize,
+}
+
+impl Simulation for Cavern {
+    type State = Vec<u32>;
+
+    fn step(&mut self) {
+        self.last_flashes = run_step(&mut self.octopuses);
+        self.total_flashes += self.last_flashes;
+    }
+
+    fn state(&self) -> Vec<u32> {
+        (0..N)
+            .cartesian_product(0..N)
+            .map(|pos| self.octopuses[&pos].energy)
+            .collect()
+    }
+}
+
 impl Solver {
-    fn parse_input(&self, input: Lines) -> HashMap<(usize, usize), Octopus> {
-        input
+    fn parse_input(&self, input: Input) -> Cavern {
+        let octopuses = input
             .flat_map(|line| line.chars())
             .enumerate()
             .map(|(k, c)| ((k / 10, k % 10), Octopus::from(c)))
-            .collect()
+            .collect();
+
+        Cavern {
+            octopuses,
+            last_flashes: 0,
+            total_flashes: 0,
+        }
     }
 }
 
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
-        let mut octopuses = self.parse_input(input);
+    fn solve_a(&self, input: Input) -> usize {
+        let mut cavern = self.parse_input(input);
 
-        iter::repeat_with(|| run_step(&mut octopuses))
-            .take(100)
-            .sum()
+        cavern.run(100);
+        cavern.total_flashes
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
-        let mut octopuses = self.parse_input(input);
+    fn solve_b(&self, input: Input) -> usize {
+        let mut cavern = self.parse_input(input);
 
-        1 + iter::repeat_with(|| run_step(&mut octopuses))
-            .find_position(|&flashes| flashes == N * N)
-            .unwrap()
-            .0
+        cavern.run_until(|c| c.last_flashes == N * N)
     }
 }
 
@@ -128,10 +153,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 1656);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 195);
     }
 }
@@ -1,8 +1,11 @@
-use adventofcode::{self as aoc, aoc_problem, map, Solution};
+use adventofcode::util::memo::Memo;
+use adventofcode::{
+    self as aoc, aoc_problem, make_err, map, util::problem::Part, Input, Solution,
+};
 use itertools::Itertools;
-use std::cell::RefCell;
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use std::str::Lines;
+use std::fmt;
 
 #[derive(Debug, Hash, Eq, PartialEq)]
 enum Cave {
@@ -28,67 +31,276 @@ impl From<&str> for Cave {
     }
 }
 
+impl fmt::Display for Cave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cave::Start => write!(f, "start"),
+            Cave::End => write!(f, "end"),
+            Cave::Small(nm) | Cave::Big(nm) => write!(f, "{}", nm),
+        }
+    }
+}
+
 type CaveSystem = HashMap<Cave, Vec<Cave>>;
 
+/// Rules for entering the caves along a path.
+///
+/// The explorer never goes back to the start, and stops at the end,
+/// so the policy only decides about the other caves. Closures
+/// `Fn(&[&Cave], &Cave) -> bool` can be used as policies too.
+trait VisitPolicy {
+    /// Returns `true` if `cave` can be entered after following `path`.
+    fn can_visit(&self, path: &[&Cave], cave: &Cave) -> bool;
+}
+
+impl<F: Fn(&[&Cave], &Cave) -> bool> VisitPolicy for F {
+    fn can_visit(&self, path: &[&Cave], cave: &Cave) -> bool {
+        self(path, cave)
+    }
+}
+
 enum CanVisit {
-    OnlyOnce,
+    SmallCavesAtMost(usize),
     OneSmallCaveTwice,
 }
 
-struct CaveExplorer<'a> {
+impl VisitPolicy for CanVisit {
+    fn can_visit(&self, path: &[&Cave], cave: &Cave) -> bool {
+        if !matches!(cave, Cave::Small(_)) {
+            return true;
+        }
+        match self {
+            CanVisit::SmallCavesAtMost(times) => {
+                path.iter().filter(|&&cv| cv == cave).count() < *times
+            }
+            CanVisit::OneSmallCaveTwice => {
+                !path.contains(&cave)
+                    || path
+                        .iter()
+                        .filter(|cv| matches!(cv, Cave::Small(_)))
+                        .all_unique()
+            }
+        }
+    }
+}
+
+struct CaveExplorer<'a, P> {
     caves: &'a CaveSystem,
-    seen: RefCell<Vec<&'a Cave>>,
-    visit_small_caves: CanVisit,
+    policy: P,
 }
 
-impl<'a> CaveExplorer<'a> {
-    fn new(caves: &'a CaveSystem, visit_small_caves: CanVisit) -> Self {
-        Self {
-            caves,
-            seen: RefCell::new(vec![]),
-            visit_small_caves,
+impl<'a, P: VisitPolicy> CaveExplorer<'a, P> {
+    fn new(caves: &'a CaveSystem, policy: P) -> Self {
+        Self { caves, policy }
+    }
+
+    /// Returns the paths from the start to the end, one by one.
+    fn paths(&self) -> Paths<'a, '_, P> {
+        let (path, next) = match self.caves.get_key_value(&Cave::Start) {
+            Some((start, _)) => (vec![start], vec![0]),
+            None => (vec![], vec![]),
+        };
+        Paths {
+            caves: self.caves,
+            policy: &self.policy,
+            path,
+            next,
         }
     }
 
-    fn validate_path_for(&self, cv: &Cave) -> bool {
-        let seen = self.seen.borrow();
-        match self.visit_small_caves {
-            CanVisit::OnlyOnce => !seen.contains(&cv),
-            CanVisit::OneSmallCaveTwice => {
-                !seen.contains(&cv)
-                    || (matches!(cv, Cave::Small(_)) && seen.iter().all_unique())
+    /// Returns the cave system in the Graphviz DOT format, labelling every passage
+    /// with the number of paths that go through it. The `highlight` most travelled
+    /// passages are drawn in red.
+    fn to_dot(&self, highlight: usize) -> String {
+        let edge = |from: &'a Cave, to: &'a Cave| {
+            if from.to_string() < to.to_string() {
+                (from, to)
+            } else {
+                (to, from)
+            }
+        };
+
+        let mut travelled: HashMap<_, usize> = self
+            .caves
+            .iter()
+            .flat_map(|(from, to)| to.iter().map(move |to| (edge(from, to), 0)))
+            .collect();
+        for path in self.paths() {
+            for (from, to) in path.into_iter().tuple_windows() {
+                *travelled.entry(edge(from, to)).or_default() += 1;
+            }
+        }
+
+        let names = |(from, to): &(&Cave, &Cave)| (from.to_string(), to.to_string());
+        let edges = travelled
+            .into_iter()
+            .sorted_by_key(|(edge, count)| (Reverse(*count), names(edge)))
+            .enumerate()
+            .map(|(rank, (edge, count))| (edge, count, rank < highlight))
+            .sorted_by_key(|(edge, _, _)| names(edge));
+
+        let mut lines = vec!["graph caves {".to_string()];
+        for cave in self.caves.keys().sorted_by_key(|cave| cave.to_string()) {
+            let shape = match cave {
+                Cave::Start | Cave::End => "doublecircle",
+                Cave::Small(_) => "circle",
+                Cave::Big(_) => "box",
+            };
+            lines.push(format!("    \"{}\" [shape={}];", cave, shape));
+        }
+        for ((from, to), count, highlighted) in edges {
+            let style = if highlighted {
+                ", color=red, penwidth=3"
+            } else {
+                ""
+            };
+            lines.push(format!(
+                "    \"{}\" -- \"{}\" [label={}{}];",
+                from, to, count, style
+            ));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+/// Cave system with the caves numbered, to count the paths without listing them.
+struct CaveGraph {
+    /// Passages out of every cave.
+    passages: Vec<Vec<usize>>,
+    /// Bit of every small cave in the set of visited caves, or 0 for the other caves.
+    bits: Vec<u64>,
+    start: usize,
+    end: usize,
+}
+
+impl TryFrom<&CaveSystem> for CaveGraph {
+    type Error = aoc::Error;
+
+    fn try_from(caves: &CaveSystem) -> Result<Self, Self::Error> {
+        let mut ids: HashMap<&Cave, usize> = caves
+            .keys()
+            .sorted_by_key(|cave| cave.to_string())
+            .enumerate()
+            .map(|(id, cave)| (cave, id))
+            .collect();
+        for cave in caves.values().flatten() {
+            let id = ids.len();
+            ids.entry(cave).or_insert(id);
+        }
+
+        let mut graph = CaveGraph {
+            passages: vec![vec![]; ids.len()],
+            bits: vec![0; ids.len()],
+            start: ids.get(&Cave::Start).copied().unwrap_or(usize::MAX),
+            end: ids.get(&Cave::End).copied().unwrap_or(usize::MAX),
+        };
+
+        let mut small = 0;
+        for (cave, &id) in ids.iter().sorted_by_key(|(_, &id)| id) {
+            if let Cave::Small(_) = cave {
+                if small == u64::BITS {
+                    return make_err!(
+                        "Too many small caves, at most {} expected",
+                        small
+                    );
+                }
+                graph.bits[id] = 1 << small;
+                small += 1;
+            }
+            if let Some(to) = caves.get(cave) {
+                graph.passages[id] = to.iter().map(|cv| ids[cv]).collect();
             }
         }
+        Ok(graph)
+    }
+}
+
+impl CaveGraph {
+    /// Counts the paths from the start to the end that visit small caves at most once,
+    /// except for one small cave that can be visited twice, if `twice` is set.
+    fn count_paths(&self, twice: bool) -> usize {
+        if self.start >= self.passages.len() {
+            return 0;
+        }
+        let mut memo = Memo::new();
+        self.count_paths_from(&mut memo, self.start, 0, twice)
     }
 
-    fn count_paths_to_exit_from(&self, cur: &'a Cave) -> usize {
-        match cur {
-            Cave::End => 1,
-            cave if self.validate_path_for(cave) => {
-                if !matches!(cave, Cave::Big(_)) {
-                    self.seen.borrow_mut().push(cave);
+    fn count_paths_from(
+        &self,
+        memo: &mut Memo<(usize, u64, bool), usize>,
+        cur: usize,
+        visited: u64,
+        twice: bool,
+    ) -> usize {
+        memo.cached((cur, visited, twice), |memo| {
+            let mut count = 0;
+            for &next in &self.passages[cur] {
+                let bit = self.bits[next];
+                count += match next {
+                    _ if next == self.start => 0,
+                    _ if next == self.end => 1,
+                    _ if visited & bit == 0 => {
+                        self.count_paths_from(memo, next, visited | bit, twice)
+                    }
+                    _ if twice => self.count_paths_from(memo, next, visited, false),
+                    _ => 0,
+                };
+            }
+            count
+        })
+    }
+}
+
+/// Lazy depth-first iterator over the paths through the cave system.
+struct Paths<'a, 'p, P> {
+    caves: &'a CaveSystem,
+    policy: &'p P,
+    /// Caves on the current path.
+    path: Vec<&'a Cave>,
+    /// Index of the next passage to try out of every cave on the path.
+    next: Vec<usize>,
+}
+
+impl<'a, 'p, P: VisitPolicy> Iterator for Paths<'a, 'p, P> {
+    type Item = Vec<&'a Cave>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&cur) = self.path.last() {
+            let idx = self.next.last_mut().unwrap();
+            let next = match self.caves.get(cur).and_then(|to| to.get(*idx)) {
+                Some(next) => next,
+                None => {
+                    self.path.pop();
+                    self.next.pop();
+                    continue;
                 }
-                let count = self
-                    .caves
-                    .get(cave)
-                    .unwrap()
-                    .iter()
-                    .map(|next| self.count_paths_to_exit_from(next))
-                    .sum();
-                if !matches!(cave, Cave::Big(_)) {
-                    self.seen.borrow_mut().pop();
+            };
+            *idx += 1;
+
+            match next {
+                Cave::Start => {}
+                Cave::End => {
+                    let mut path = self.path.clone();
+                    path.push(next);
+                    return Some(path);
                 }
-                count
+                cave if self.policy.can_visit(&self.path, cave) => {
+                    self.path.push(cave);
+                    self.next.push(0);
+                }
+                _ => {}
             }
-            _ => 0,
         }
+        None
     }
 }
 
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, input: Lines) -> CaveSystem {
+    fn parse_input(&self, input: Input) -> CaveSystem {
         let mut caves: CaveSystem = map![];
 
         input
@@ -108,16 +320,25 @@ impl Solver {
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         let caves = self.parse_input(input);
-        let explorer = CaveExplorer::new(&caves, CanVisit::OnlyOnce);
-        explorer.count_paths_to_exit_from(&Cave::Start)
+        CaveGraph::try_from(&caves).unwrap().count_paths(false)
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
         let caves = self.parse_input(input);
-        let explorer = CaveExplorer::new(&caves, CanVisit::OneSmallCaveTwice);
-        explorer.count_paths_to_exit_from(&Cave::Start)
+        CaveGraph::try_from(&caves).unwrap().count_paths(true)
+    }
+
+    fn explain(&self, part: Part, input: Input) -> Option<String> {
+        let caves = self.parse_input(input);
+        let dot = match part {
+            Part::A => {
+                CaveExplorer::new(&caves, CanVisit::SmallCavesAtMost(1)).to_dot(1)
+            }
+            Part::B => CaveExplorer::new(&caves, CanVisit::OneSmallCaveTwice).to_dot(1),
+        };
+        Some(dot)
     }
 }
 
@@ -135,7 +356,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use aoc::StripMargin;
+    use aoc::{util::random::Lcg, StripMargin};
 
     #[test]
     fn test_example_1() {
@@ -152,10 +373,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 10);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 36);
     }
 
@@ -177,10 +398,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 19);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 103);
     }
 
@@ -210,10 +431,182 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 226);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 3509);
     }
+
+    fn example_1() -> String {
+        r"
+            |start-A
+            |start-b
+            |A-c
+            |A-b
+            |b-d
+            |A-end
+            |b-end
+        "
+        .trim()
+        .strip_margin()
+    }
+
+    #[test]
+    fn test_paths() {
+        let caves = Solver {}.parse_input(Input::new(&example_1()));
+        let explorer = CaveExplorer::new(&caves, CanVisit::SmallCavesAtMost(1));
+        let paths: Vec<_> = explorer
+            .paths()
+            .map(|path| path.iter().join(","))
+            .sorted()
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "start,A,b,A,c,A,end",
+                "start,A,b,A,end",
+                "start,A,b,end",
+                "start,A,c,A,b,A,end",
+                "start,A,c,A,b,end",
+                "start,A,c,A,end",
+                "start,A,end",
+                "start,b,A,c,A,end",
+                "start,b,A,end",
+                "start,b,end",
+            ]
+        );
+
+        // every small cave twice, or no small caves at all
+        let explorer = CaveExplorer::new(&caves, CanVisit::SmallCavesAtMost(2));
+        assert_eq!(explorer.paths().count(), 54);
+        let explorer = CaveExplorer::new(&caves, CanVisit::SmallCavesAtMost(0));
+        assert_eq!(explorer.paths().count(), 1);
+
+        // closures work as policies too
+        let forbidden = Cave::from("c");
+        let policy = |path: &[&Cave], cave: &Cave| {
+            cave != &forbidden && CanVisit::SmallCavesAtMost(1).can_visit(path, cave)
+        };
+        let explorer = CaveExplorer::new(&caves, policy);
+        assert!(explorer.paths().all(|path| !path.contains(&&forbidden)));
+        assert_eq!(explorer.paths().count(), 5);
+    }
+
+    #[test]
+    fn test_dot() {
+        let caves = Solver {}.parse_input(Input::new(&example_1()));
+        let explorer = CaveExplorer::new(&caves, CanVisit::SmallCavesAtMost(1));
+
+        let expected = r#"
+            |graph caves {
+            |    "A" [shape=box];
+            |    "b" [shape=circle];
+            |    "c" [shape=circle];
+            |    "d" [shape=circle];
+            |    "end" [shape=doublecircle];
+            |    "start" [shape=doublecircle];
+            |    "A" -- "b" [label=10, color=red, penwidth=3];
+            |    "A" -- "c" [label=10];
+            |    "A" -- "end" [label=7];
+            |    "A" -- "start" [label=7];
+            |    "b" -- "d" [label=0];
+            |    "b" -- "end" [label=3];
+            |    "b" -- "start" [label=3];
+            |}
+        "#
+        .trim()
+        .strip_margin();
+        assert_eq!(explorer.to_dot(1), expected);
+
+        let dot = explorer.to_dot(3);
+        assert_eq!(dot.matches("color=red").count(), 3);
+        assert_eq!(
+            Solver {}.explain(Part::A, Input::new(&example_1())),
+            Some(expected)
+        );
+        assert!(dot.contains("\"A\" -- \"end\" [label=7, color=red, penwidth=3];"));
+    }
+
+    /// Generates a cave system with the given number of big and small caves,
+    /// where every small cave is connected to about `degree` other caves.
+    /// Big caves are never connected to each other, so that the paths are finite.
+    fn generate(big: usize, small: usize, degree: usize, seed: u64) -> String {
+        let mut rng = Lcg::new(seed);
+        let mut next = |n: usize| rng.below(n as u64) as usize;
+
+        let mut names = vec!["start".to_string(), "end".to_string()];
+        names.extend((0..small).map(|k| format!("s{}", k)));
+        names.extend((0..big).map(|k| format!("B{}", k)));
+
+        let mut passages = vec![];
+        for from in 0..small + 2 {
+            for _ in 0..degree {
+                let to = next(names.len());
+                let (a, b) = (from.min(to), from.max(to));
+                if a != b && (a, b) != (0, 1) && !passages.contains(&(a, b)) {
+                    passages.push((a, b));
+                }
+            }
+        }
+        passages
+            .into_iter()
+            .map(|(a, b)| format!("{}-{}", names[a], names[b]))
+            .join("\n")
+    }
+
+    #[test]
+    fn test_generated_graphs() {
+        let solver = Solver {};
+        for seed in 0..20 {
+            let input = generate(2, 6, 3, seed);
+            let caves = solver.parse_input(Input::new(&input));
+            let graph = CaveGraph::try_from(&caves).unwrap();
+            for (twice, policy) in [
+                (false, CanVisit::SmallCavesAtMost(1)),
+                (true, CanVisit::OneSmallCaveTwice),
+            ] {
+                let explorer = CaveExplorer::new(&caves, policy);
+                assert_eq!(graph.count_paths(twice), explorer.paths().count());
+            }
+        }
+
+        let input = generate(0, 70, 2, 1);
+        let caves = solver.parse_input(Input::new(&input));
+        let err = CaveGraph::try_from(&caves).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Error: Too many small caves, at most 64 expected"
+        );
+    }
+
+    /// Compares the memoized counting with listing all the paths one by one:
+    /// `cargo test --release --bin day12 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_generated_graphs() {
+        use std::time::Instant;
+
+        let solver = Solver {};
+        for (big, small, degree) in [(2, 8, 3), (3, 10, 3), (3, 12, 3)] {
+            let input = generate(big, small, degree, 42);
+            let caves = solver.parse_input(Input::new(&input));
+
+            let now = Instant::now();
+            let graph = CaveGraph::try_from(&caves).unwrap();
+            let fast = graph.count_paths(true);
+            let fast_time = now.elapsed();
+
+            let now = Instant::now();
+            let explorer = CaveExplorer::new(&caves, CanVisit::OneSmallCaveTwice);
+            let slow = explorer.paths().count();
+            let slow_time = now.elapsed();
+
+            assert_eq!(fast, slow);
+            println!(
+                "{} big, {} small caves: {} paths, memoized {:?}, listed {:?}",
+                big, small, fast, fast_time, slow_time
+            );
+        }
+    }
 }
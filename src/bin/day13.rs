@@ -1,7 +1,6 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
 use std::collections::HashSet;
 use std::fmt;
-use std::str::Lines;
 
 struct Solver;
 
@@ -31,16 +30,19 @@ impl fmt::Display for Answer {
 }
 
 impl Solver {
-    fn parse_input(&self, input: Lines) -> (Dots, Instructions) {
-        let dots: Dots = input
-            .clone()
-            .filter(|line| line.contains(','))
+    fn parse_input(&self, input: Input) -> (Dots, Instructions) {
+        let mut sections = input.sections();
+
+        let dots: Dots = sections
+            .next()
+            .expect("Expect dots")
             .map(|line| line.split_once(',').expect("Expect one delimiter"))
             .map(|(x, y)| (x.parse().unwrap(), y.parse().unwrap()))
             .collect();
 
-        let instr: Instructions = input
-            .filter(|line| line.contains('='))
+        let instr: Instructions = sections
+            .next()
+            .expect("Expect fold instructions")
             .map(|line| {
                 line.strip_prefix("fold along ")
                     .expect("Expect valid prefix")
@@ -57,7 +59,7 @@ impl Solver {
 impl Solution for Solver {
     type Output = Answer;
 
-    fn solve_a(&self, input: Lines) -> Answer {
+    fn solve_a(&self, input: Input) -> Answer {
         let (dots, instr) = self.parse_input(input);
         let (axis, pos) = &instr[0];
 
@@ -73,7 +75,7 @@ impl Solution for Solver {
         Answer::Num(folded.len())
     }
 
-    fn solve_b(&self, input: Lines) -> Answer {
+    fn solve_b(&self, input: Input) -> Answer {
         let (dots, instr) = self.parse_input(input);
 
         let folded: HashSet<_> = dots
@@ -150,10 +152,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, Answer::Num(17));
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(
             ans,
             Answer::Str("XXXXX\nX   X\nX   X\nX   X\nXXXXX\n     \n     ".to_string())
@@ -1,92 +1,261 @@
-use adventofcode::{self as aoc, aoc_problem, map, Solution};
-use std::collections::HashMap;
-use std::str::Lines;
+use adventofcode::util::linalg::Matrix;
+use adventofcode::{
+    self as aoc, aoc_problem, make_err, map, util::problem::Part, Input, Solution,
+};
+use itertools::{Itertools, MinMaxResult};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 type Pair = [char; 2];
 
+/// Elements inserted between the two elements of every pair.
+type Rules = HashMap<Pair, String>;
+
+/// Number of every element in the polymer.
+type Histogram = BTreeMap<char, u64>;
+
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, mut input: Lines) -> (String, HashMap<Pair, Vec<Pair>>) {
-        let template = input.next().unwrap().to_string();
-        input.next();
+    fn parse_input(&self, input: Input) -> Result<(String, Rules), aoc::Error> {
+        let mut sections = input.sections();
+        let template = match sections.next().map(|s| s.as_str().trim()) {
+            Some(template) if !template.contains('\n') => template.to_string(),
+            _ => return make_err!("Expected a polymer template on the first line"),
+        };
 
-        let mut rules0 = map![];
+        let mut rules = map![];
+        for (no, line) in sections.flat_map(|s| s.numbered_lines()) {
+            let (from, to) = match line.split_once(" -> ") {
+                Some(rule) => rule,
+                None => {
+                    return make_err!(
+                        "line {}: expected `AB -> C`, found `{}`",
+                        no,
+                        line
+                    )
+                }
+            };
+            let pair: Pair = match from.chars().collect::<Vec<_>>()[..] {
+                [a, b] => [a, b],
+                _ => {
+                    return make_err!(
+                        "line {}: rule should match a pair of elements, found `{}`",
+                        no,
+                        from
+                    )
+                }
+            };
+            if rules.insert(pair, to.to_string()).is_some() {
+                return make_err!("line {}: duplicate rule for `{}`", no, from);
+            }
+        }
 
-        for line in input {
-            let (from, to) = line.split_once(" -> ").expect("Expect one delimiter");
-            let fr: Pair = [from.chars().next().unwrap(), from.chars().nth(1).unwrap()];
-            rules0.insert(fr, to.chars().next().unwrap());
+        let missing = missing_rules(&template, &rules);
+        if !missing.is_empty() {
+            let missing = missing
+                .iter()
+                .map(|p| p.iter().collect::<String>())
+                .join(", ");
+            return make_err!("No rules for the pairs {}", missing);
         }
 
-        let mut rules: HashMap<Pair, Vec<Pair>> = map![];
-        for (&k, &v) in rules0.iter() {
-            for vv in [[k[0], v], [v, k[1]]] {
-                if rules0.contains_key(&vv) {
-                    rules.entry(k).or_default().push(vv);
-                }
+        Ok((template, rules))
+    }
+}
+
+/// Returns the pairs of elements after inserting the elements between the pair.
+fn expand(pair: Pair, rules: &Rules) -> Vec<Pair> {
+    let inserted = rules.get(&pair).map_or("", String::as_str);
+    let elements: Vec<char> = [pair[0]]
+        .into_iter()
+        .chain(inserted.chars())
+        .chain([pair[1]])
+        .collect();
+    elements.windows(2).map(|w| [w[0], w[1]]).collect()
+}
+
+fn template_pairs(template: &str) -> impl Iterator<Item = Pair> + Clone + '_ {
+    template.chars().tuple_windows().map(|(a, b)| [a, b])
+}
+
+/// Returns the pairs that can appear in the polymer, but have no insertion rules.
+fn missing_rules(template: &str, rules: &Rules) -> Vec<Pair> {
+    let mut seen: HashSet<Pair> = template_pairs(template).collect();
+    let mut queue: VecDeque<Pair> = seen.iter().copied().collect();
+    let mut missing = vec![];
+
+    while let Some(pair) = queue.pop_front() {
+        if !rules.contains_key(&pair) {
+            missing.push(pair);
+            continue;
+        }
+        for next in expand(pair, rules) {
+            if seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    missing.sort_unstable();
+    missing
+}
+
+/// Returns the histogram of the elements after every step, starting from
+/// the template itself, for as long as the counts fit into `u64`.
+fn histograms<'a>(
+    template: &'a str,
+    rules: &'a Rules,
+) -> impl Iterator<Item = Histogram> + 'a {
+    let mut pairs: HashMap<Pair, u64> = map![];
+    template_pairs(template).for_each(|pair| *pairs.entry(pair).or_default() += 1);
+
+    let steps = std::iter::successors(Some(pairs), move |pairs| {
+        let mut next: HashMap<Pair, u64> = map![];
+        for (&pair, &count) in pairs {
+            for p in expand(pair, rules) {
+                let cnt = next.entry(p).or_default();
+                *cnt = cnt.checked_add(count)?;
             }
         }
-        (template, rules)
+        Some(next)
+    });
+
+    let last = template.chars().last();
+    steps.map_while(move |pairs| {
+        // every element except the last one starts a pair
+        let mut hist: Histogram = last.into_iter().map(|el| (el, 1)).collect();
+        for (pair, count) in pairs {
+            let cnt = hist.entry(pair[0]).or_default();
+            *cnt = cnt.checked_add(count)?;
+        }
+        Some(hist)
+    })
+}
+
+/// Returns the difference between the most and the least common elements.
+fn spread(hist: &Histogram) -> u64 {
+    match hist.values().minmax() {
+        MinMaxResult::MinMax(min, max) => max - min,
+        _ => 0,
     }
 }
 
-fn find_quantities(
-    template: String,
-    rules: HashMap<Pair, Vec<Pair>>,
-    iterations: usize,
-) -> usize {
-    let initial = template.chars().collect::<Vec<_>>();
-
-    let odd = [
-        initial.first().copied().unwrap(),
-        initial.last().copied().unwrap(),
-    ];
-    let mut initial: HashMap<Pair, usize> = initial
-        .windows(2)
-        .map(|w| [w[0], w[1]])
-        .fold(map![], |mut m, el| {
-            *m.entry(el).or_default() += 1;
-            m
-        });
-
-    for _ in 0..iterations {
-        let updated: HashMap<Pair, usize> = initial
-            .iter()
-            .flat_map(|(&k, &v)| rules.get(&k).unwrap().iter().map(move |&el| (el, v)))
-            .fold(map![], |mut m, (k, v)| {
-                *m.entry(k).or_default() += v;
-                m
-            });
-        initial = updated;
-    }
-
-    let counts =
-        initial
-            .iter()
-            .fold(map![odd[0] => 1, odd[1] => 1], |mut m, (k, &v)| {
-                *m.entry(k[0]).or_default() += v;
-                *m.entry(k[1]).or_default() += v;
-                m
-            });
-
-    let minc = counts.values().min().copied().unwrap();
-    let maxc = counts.values().max().copied().unwrap();
-
-    (maxc - minc) / 2
+/// Returns the element that is more common than each of the others, if any.
+fn dominant(hist: &Histogram) -> Option<char> {
+    let mut counts = hist
+        .iter()
+        .sorted_by_key(|&(_, &cnt)| std::cmp::Reverse(cnt));
+    match (counts.next(), counts.next()) {
+        (Some((&el, _)), None) => Some(el),
+        (Some((&el, first)), Some((_, second))) if first > second => Some(el),
+        _ => None,
+    }
+}
+
+/// Finds the first step, up to `max_steps`, after which `element` is
+/// more common than each of the other elements.
+fn first_dominant_step(
+    template: &str,
+    rules: &Rules,
+    element: char,
+    max_steps: usize,
+) -> Option<usize> {
+    histograms(template, rules)
+        .take(max_steps + 1)
+        .position(|hist| dominant(&hist) == Some(element))
+}
+
+fn find_quantities(template: &str, rules: &Rules, iterations: usize) -> usize {
+    let hist = histograms(template, rules)
+        .nth(iterations)
+        .expect("Element counts don't fit into u64");
+    spread(&hist) as usize
+}
+
+/// Counts the elements after any number of steps via the transition matrix
+/// of the pair counts, or returns `Err` if the counts don't fit into `u64`.
+fn find_quantities_far(
+    template: &str,
+    rules: &Rules,
+    steps: u64,
+) -> Result<u64, aoc::Error> {
+    let mut index: HashMap<Pair, usize> = map![];
+    let rule_pairs = rules
+        .keys()
+        .flat_map(|&p| [p].into_iter().chain(expand(p, rules)));
+    for pair in template_pairs(template).chain(rule_pairs) {
+        let n = index.len();
+        index.entry(pair).or_insert(n);
+    }
+
+    let mut m = Matrix::zeros(index.len(), index.len());
+    for (&pair, &i) in &index {
+        for next in expand(pair, rules) {
+            m[(index[&next], i)] += 1;
+        }
+    }
+
+    let mut pairs = vec![0; index.len()];
+    template_pairs(template).for_each(|pair| pairs[index[&pair]] += 1);
+    let pairs = m.checked_pow(steps)?.checked_mul_vec(&pairs)?;
+
+    // every element except the last one starts a pair
+    let mut counts: Histogram = template
+        .chars()
+        .last()
+        .map(|el| (el, 1))
+        .into_iter()
+        .collect();
+    for (&pair, &i) in &index {
+        let cnt = counts.entry(pair[0]).or_default();
+        *cnt = cnt
+            .checked_add(pairs[i])
+            .ok_or_else(|| aoc::Error::new("Integer overflow in element counts"))?;
+    }
+    counts.retain(|_, &mut cnt| cnt > 0);
+
+    Ok(spread(&counts))
 }
 
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
-        let (template, rules) = self.parse_input(input);
-        find_quantities(template, rules, 10)
+    fn solve_a(&self, input: Input) -> usize {
+        let (template, rules) = self.parse_input(input).unwrap();
+        find_quantities(&template, &rules, 10)
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
-        let (template, rules) = self.parse_input(input);
-        find_quantities(template, rules, 40)
+    fn solve_b(&self, input: Input) -> usize {
+        let (template, rules) = self.parse_input(input).unwrap();
+        find_quantities_far(&template, &rules, 40).unwrap() as usize
+    }
+
+    fn explain(&self, part: Part, input: Input) -> Option<String> {
+        let (template, rules) = self.parse_input(input).unwrap();
+        let steps = match part {
+            Part::A => 10,
+            Part::B => 40,
+        };
+
+        let mut lines = vec![];
+        let mut elements = vec![];
+        for (step, hist) in histograms(&template, &rules).take(steps + 1).enumerate() {
+            let counts = hist
+                .iter()
+                .map(|(el, cnt)| format!("{}={}", el, cnt))
+                .join(" ");
+            let most = match dominant(&hist) {
+                Some(el) => format!(", {} leads", el),
+                None => String::new(),
+            };
+            lines.push(format!("step {:>2}: {}{}", step, counts, most));
+            elements = hist.into_keys().collect();
+        }
+        for el in elements {
+            if let Some(step) = first_dominant_step(&template, &rules, el, steps) {
+                lines.push(format!("{} first leads after step {}", el, step));
+            }
+        }
+        Some(lines.join("\n"))
     }
 }
 
@@ -106,36 +275,176 @@ mod tests {
     use super::*;
     use aoc::StripMargin;
 
+    fn example() -> String {
+        r"
+        |NNCB
+        |
+        |CH -> B
+        |HH -> N
+        |CB -> H
+        |NH -> C
+        |HB -> C
+        |HC -> B
+        |HN -> C
+        |NN -> C
+        |BH -> H
+        |NC -> B
+        |NB -> B
+        |BN -> B
+        |BB -> N
+        |BC -> B
+        |CC -> N
+        |CN -> C
+        "
+        .trim()
+        .strip_margin()
+    }
+
     #[test]
     fn test_solution() {
         let solver = Solver {};
+        let input = example();
+
+        let ans = solver.solve_a(Input::new(&input));
+        assert_eq!(ans, 1588);
+
+        let ans = solver.solve_b(Input::new(&input));
+        assert_eq!(ans, 2_188_189_693_529);
+
+        let text = solver.explain(Part::A, Input::new(&input)).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 11 + 2);
+        assert_eq!(lines[1], "step  1: B=2 C=2 H=1 N=2");
+        assert_eq!(lines[10], "step 10: B=1749 C=298 H=161 N=865, B leads");
+        assert_eq!(
+            lines[11..],
+            ["B first leads after step 2", "N first leads after step 0"]
+        );
+    }
+
+    #[test]
+    fn test_far_future() {
+        let solver = Solver {};
+        let input = example();
+        let (template, rules) = solver.parse_input(Input::new(&input)).unwrap();
+
+        for steps in 0..=20 {
+            assert_eq!(
+                find_quantities_far(&template, &rules, steps as u64).unwrap() as usize,
+                find_quantities(&template, &rules, steps)
+            );
+        }
+        assert!(find_quantities_far(&template, &rules, 1_000_000_000_000_000).is_err());
+    }
+
+    /// Grows the polymer by inserting the elements one by one.
+    fn grow(template: &str, rules: &Rules, steps: usize) -> String {
+        let mut polymer = template.to_string();
+        for _ in 0..steps {
+            let mut next: String = polymer.chars().take(1).collect();
+            for (a, b) in polymer.chars().tuple_windows() {
+                next.push_str(&rules[&[a, b]]);
+                next.push(b);
+            }
+            polymer = next;
+        }
+        polymer
+    }
+
+    fn histogram(polymer: &str) -> Histogram {
+        let mut hist = Histogram::new();
+        polymer
+            .chars()
+            .for_each(|el| *hist.entry(el).or_default() += 1);
+        hist
+    }
+
+    #[test]
+    fn test_histograms() {
+        let solver = Solver {};
+        let input = example();
+        let (template, rules) = solver.parse_input(Input::new(&input)).unwrap();
+
+        let hists: Vec<_> = histograms(&template, &rules).take(11).collect();
+        assert_eq!(hists[1], histogram("NCNBCHB"));
+        assert_eq!(
+            hists[10]
+                .iter()
+                .map(|(&el, &cnt)| (el, cnt))
+                .collect::<Vec<_>>(),
+            vec![('B', 1749), ('C', 298), ('H', 161), ('N', 865)]
+        );
+        for (steps, hist) in hists.iter().enumerate() {
+            assert_eq!(hist, &histogram(&grow(&template, &rules, steps)));
+        }
+
+        assert_eq!(dominant(&hists[0]), Some('N'));
+        assert_eq!(dominant(&hists[10]), Some('B'));
+        assert_eq!(dominant(&histogram("ABBA")), None);
+        assert_eq!(first_dominant_step(&template, &rules, 'N', 10), Some(0));
+        let step = first_dominant_step(&template, &rules, 'B', 10).unwrap();
+        assert!(hists[..step].iter().all(|h| dominant(h) != Some('B')));
+        assert_eq!(first_dominant_step(&template, &rules, 'H', 10), None);
+
+        // the counts keep growing until they overflow
+        assert_eq!(histograms(&template, &rules).count(), 64);
+    }
+
+    #[test]
+    fn test_longer_rules() {
         let input = r"
-            |NNCB
+            |ABC
             |
-            |CH -> B
-            |HH -> N
-            |CB -> H
-            |NH -> C
-            |HB -> C
-            |HC -> B
-            |HN -> C
-            |NN -> C
-            |BH -> H
-            |NC -> B
-            |NB -> B
-            |BN -> B
-            |BB -> N
-            |BC -> B
-            |CC -> N
-            |CN -> C
+            |AA -> B
+            |AB -> CA
+            |AC -> B
+            |BA -> AAC
+            |BB -> C
+            |BC -> BA
+            |CA -> A
+            |CB -> BC
+            |CC -> AB
         "
         .trim()
         .strip_margin();
+        let (template, rules) = Solver {}.parse_input(Input::new(&input)).unwrap();
+        assert_eq!(grow(&template, &rules, 1), "ACABBAC");
 
-        let ans = solver.solve_a(input.lines());
-        assert_eq!(ans, 1588);
+        for (steps, hist) in histograms(&template, &rules).take(8).enumerate() {
+            let polymer = grow(&template, &rules, steps);
+            assert_eq!(hist, histogram(&polymer));
+            assert_eq!(
+                find_quantities_far(&template, &rules, steps as u64).unwrap(),
+                spread(&hist)
+            );
+        }
+    }
 
-        let ans = solver.solve_b(input.lines());
-        assert_eq!(ans, 2_188_189_693_529);
+    #[test]
+    fn test_invalid_rules() {
+        let solver = Solver {};
+        let missing = example().replace("CC -> N\n", "").replace("BB -> N\n", "");
+        let with_extra = format!("{}\nAB -> C", example());
+
+        for (input, err) in [
+            (missing.as_str(), "No rules for the pairs BB, CC"),
+            ("", "Expected a polymer template on the first line"),
+            (
+                "NN\n\nNN => C",
+                "line 3: expected `AB -> C`, found `NN => C`",
+            ),
+            (
+                "NN\n\nNNN -> C",
+                "line 3: rule should match a pair of elements, found `NNN`",
+            ),
+            ("NN\n\nNN -> C\nNN -> B", "line 4: duplicate rule for `NN`"),
+            ("NB\n\nNB -> C\nNC -> B", "No rules for the pairs BC, CB"),
+        ] {
+            let actual = solver.parse_input(Input::new(input)).unwrap_err();
+            assert_eq!(actual.to_string(), format!("Error: {}", err), "{}", input);
+        }
+
+        // rules for the pairs that never appear are fine
+        assert!(solver.parse_input(Input::new(&with_extra)).is_ok());
     }
 }
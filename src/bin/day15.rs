@@ -1,11 +1,10 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
 use std::collections::BinaryHeap;
-use std::str::Lines;
 
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, input: Lines) -> Vec<Vec<i32>> {
+    fn parse_input(&self, input: Input) -> Vec<Vec<i32>> {
         input
             .map(|line| {
                 line.chars()
@@ -77,13 +76,13 @@ fn dijkstra(grid: Vec<Vec<i32>>) -> Option<i32> {
 impl Solution for Solver {
     type Output = i32;
 
-    fn solve_a(&self, input: Lines) -> i32 {
+    fn solve_a(&self, input: Input) -> i32 {
         let grid = self.parse_input(input);
 
         dijkstra(grid).unwrap()
     }
 
-    fn solve_b(&self, input: Lines) -> i32 {
+    fn solve_b(&self, input: Input) -> i32 {
         let gr = self.parse_input(input);
         let n = gr.len();
 
@@ -137,10 +136,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 40);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 315);
     }
 }
@@ -1,8 +1,10 @@
-use adventofcode::{self as aoc, aoc_problem, make_err, Solution};
+use adventofcode::{self as aoc, aoc_problem, make_err, Input, Solution};
 use itertools::Itertools;
-use std::str::Lines;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum Op {
     Sum,
     Product,
@@ -33,126 +35,569 @@ impl TryFrom<u8> for Op {
     }
 }
 
-#[derive(Debug)]
+impl From<Op> for u8 {
+    fn from(op: Op) -> Self {
+        match op {
+            Op::Sum => 0,
+            Op::Product => 1,
+            Op::Min => 2,
+            Op::Max => 3,
+            Op::Greater => 5,
+            Op::Less => 6,
+            Op::Equal => 7,
+        }
+    }
+}
+
+impl Op {
+    /// Name of the operation in S-expressions.
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Sum => "sum",
+            Op::Product => "product",
+            Op::Min => "min",
+            Op::Max => "max",
+            Op::Greater => "gt",
+            Op::Less => "lt",
+            Op::Equal => "eq",
+        }
+    }
+
+    /// Infix symbol and precedence of the operation, if it has one.
+    fn infix(&self) -> Option<(&'static str, u8)> {
+        match self {
+            Op::Greater => Some((">", 1)),
+            Op::Less => Some(("<", 1)),
+            Op::Equal => Some(("==", 1)),
+            Op::Sum => Some(("+", 2)),
+            Op::Product => Some(("*", 3)),
+            Op::Min | Op::Max => None,
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = aoc::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let op = match s {
+            "sum" => Op::Sum,
+            "product" => Op::Product,
+            "min" => Op::Min,
+            "max" => Op::Max,
+            "gt" => Op::Greater,
+            "lt" => Op::Less,
+            "eq" => Op::Equal,
+            _ => return make_err!("Unknown operator: {}", s),
+        };
+        Ok(op)
+    }
+}
+
+/// Which length field the encoder writes for the sub-packets of an operator.
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum LengthType {
+    /// Type 0: total length of the sub-packets in bits, in 15 bits.
+    TotalBits,
+    /// Type 1: number of the sub-packets, in 11 bits.
+    SubPackets,
+}
+
+#[derive(Debug, PartialEq)]
 struct Packet {
     version: u64,
-    bits: usize,
     value: Value,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum Value {
     Literal(u64),
     Expression(Op, Vec<Packet>),
 }
 
+/// Reads big-endian bit fields from a buffer of bytes.
+#[derive(Debug)]
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// Number of valid bits in the buffer.
+    len: usize,
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8], len: usize) -> Self {
+        assert!(len <= 8 * bytes.len(), "Buffer is too short");
+        Self { bytes, len, pos: 0 }
+    }
+
+    /// Returns the offset of the next bit to read.
+    fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Reads the next `n` bits (up to 64) as a number,
+    /// or returns `Err` if the buffer ends before.
+    fn read_bits(&mut self, n: usize) -> Result<u64, aoc::Error> {
+        assert!(n <= 64, "Can't read more than 64 bits at once");
+        if self.len - self.pos < n {
+            return make_err!(
+                "bit {}: expected {} more bits, found {}",
+                self.pos,
+                n,
+                self.len - self.pos
+            );
+        }
+
+        let mut res = 0;
+        let mut left = n;
+        while left > 0 {
+            let (byte, offset) = (self.bytes[self.pos / 8], self.pos % 8);
+            let take = left.min(8 - offset);
+            let chunk = (byte >> (8 - offset - take)) & ((1 << take) - 1) as u8;
+            res = res << take | chunk as u64;
+            self.pos += take;
+            left -= take;
+        }
+        Ok(res)
+    }
+
+    fn read_bit(&mut self) -> Result<bool, aoc::Error> {
+        self.read_bits(1).map(|b| b == 1)
+    }
+}
+
+/// Collects big-endian bit fields into a buffer of bytes.
+#[cfg(test)]
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+#[cfg(test)]
+impl BitWriter {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of bits written.
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Appends the `width` lowest bits of `value`, most significant first.
+    fn push_bits(&mut self, value: u64, width: usize) {
+        for i in (0..width).rev() {
+            if self.len % 8 == 0 {
+                self.bytes.push(0);
+            }
+            let bit = (value >> i) as u8 & 1;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.len % 8);
+            self.len += 1;
+        }
+    }
+
+    /// Appends all the bits of the other writer.
+    fn append(&mut self, other: &BitWriter) {
+        let mut reader = BitReader::new(&other.bytes, other.len);
+        while reader.position() < other.len {
+            let n = (other.len - reader.position()).min(8);
+            self.push_bits(reader.read_bits(n).unwrap(), n);
+        }
+    }
+
+    /// Converts the bits to hex, padding them with zeros to whole bytes.
+    fn to_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+}
+
+/// Parses the hex transmission into bytes, along with the number of bits.
+fn parse_hex(hex: &str) -> Result<(Vec<u8>, usize), aoc::Error> {
+    let nibbles = hex
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c.to_digit(16) {
+            Some(n) => Ok(n as u8),
+            None => make_err!("column {}: unexpected '{}'", i + 1, c),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let bytes = nibbles
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair.get(1).unwrap_or(&0))
+        .collect();
+    Ok((bytes, 4 * nibbles.len()))
+}
+
 impl Packet {
-    fn parse_literal_parts(input: &[char]) -> (u64, usize) {
-        let input = &input[6..];
-        let last = input
-            .iter()
-            .enumerate()
-            .find(|&(i, &el)| i % 5 == 0 && el == '0')
-            .unwrap()
-            .0
-            + 5;
+    fn literal(version: u64, value: u64) -> Self {
+        Self {
+            version,
+            value: Value::Literal(value),
+        }
+    }
 
-        let v = input
-            .iter()
-            .enumerate()
-            .filter(|&(i, _)| i % 5 != 0 && i < last)
-            .map(|(_, &c)| c)
-            .collect::<String>();
-        let v = u64::from_str_radix(&v, 2).unwrap();
-
-        (v, last + 6)
-    }
-
-    fn parse_operator_parts(input: &[char]) -> (Vec<Packet>, usize) {
-        let input = &input[6..];
-        let mut elements = vec![];
-        let mut read = 0;
-        match input[0] {
-            '0' => {
-                // length_id = 0
-                let total_len = input[1..16].iter().collect::<String>();
-                let total_len = usize::from_str_radix(&total_len, 2).unwrap();
-                let inp = &input[16..16 + total_len];
-                while read < total_len {
-                    elements.push(Packet::from(&inp[read..]));
-                    read += elements.last().unwrap().bits;
+    fn expression(version: u64, op: Op, elements: Vec<Packet>) -> Self {
+        Self {
+            version,
+            value: Value::Expression(op, elements),
+        }
+    }
+
+    /// Decodes the outermost packet of the hex transmission.
+    /// The bits after it are padding, and are ignored.
+    fn from_hex(hex: &str) -> Result<Self, aoc::Error> {
+        let (bytes, len) = parse_hex(hex)?;
+        Packet::decode(&mut BitReader::new(&bytes, len), None)
+    }
+
+    /// Decodes the hex transmission, and lists the header of every packet
+    /// along with its bit offset.
+    fn disassemble(hex: &str) -> Result<String, aoc::Error> {
+        let (bytes, len) = parse_hex(hex)?;
+        let mut listing = Listing::default();
+        Packet::decode(&mut BitReader::new(&bytes, len), Some(&mut listing))?;
+        Ok(listing.lines.join("\n"))
+    }
+
+    /// Decodes the packet that starts at the current position of the reader,
+    /// and adds its header to the listing, if any.
+    fn decode(
+        reader: &mut BitReader,
+        mut listing: Option<&mut Listing>,
+    ) -> Result<Self, aoc::Error> {
+        let start = reader.position();
+        let version = reader.read_bits(3)?;
+        let type_id = reader.read_bits(3)? as u8;
+
+        let packet = match type_id {
+            4 => {
+                let mut value: u64 = 0;
+                loop {
+                    let more = reader.read_bit()?;
+                    if value >> 60 != 0 {
+                        return make_err!("bit {}: literal overflows u64", start);
+                    }
+                    value = value << 4 | reader.read_bits(4)?;
+                    if !more {
+                        break;
+                    }
+                }
+                if let Some(listing) = listing {
+                    listing.add(start, version, type_id, format!("literal {}", value));
                 }
+                Packet::literal(version, value)
+            }
+            id => {
+                let op = Op::try_from(id)
+                    .map_err(|err| err.context(format!("bit {}", start + 3)))?;
+                let mut elements = vec![];
+                let length_type = reader.read_bit()?;
+                let size = reader.read_bits(if length_type { 11 } else { 15 })?;
+                if let Some(listing) = listing.as_deref_mut() {
+                    let desc = match length_type {
+                        true => format!(
+                            "{}, length type 1: {} sub-packets",
+                            op.name(),
+                            size
+                        ),
+                        false => format!("{}, length type 0: {} bits", op.name(), size),
+                    };
+                    listing.add(start, version, type_id, desc);
+                    listing.depth += 1;
+                }
+
+                if length_type {
+                    for _ in 0..size {
+                        elements.push(Packet::decode(reader, listing.as_deref_mut())?);
+                    }
+                } else {
+                    let len = size as usize;
+                    let end = reader.position() + len;
+                    while reader.position() < end {
+                        elements.push(Packet::decode(reader, listing.as_deref_mut())?);
+                    }
+                    if reader.position() > end {
+                        return make_err!(
+                            "bit {}: sub-packets overrun their length of {} bits",
+                            start,
+                            len
+                        );
+                    }
+                }
+                if let Some(listing) = listing {
+                    listing.depth -= 1;
+                }
+                Packet::expression(version, op, elements)
+            }
+        };
+        Ok(packet)
+    }
+
+    /// Appends the BITS encoding of the packet to `bits`. Operators use
+    /// the preferred length type, unless their sub-packets don't fit it.
+    #[cfg(test)]
+    fn encode(
+        &self,
+        bits: &mut BitWriter,
+        prefer: LengthType,
+    ) -> Result<(), aoc::Error> {
+        if self.version >= 8 {
+            return make_err!("Version {} doesn't fit into 3 bits", self.version);
+        }
+        bits.push_bits(self.version, 3);
 
-                read += 22;
+        match &self.value {
+            Value::Literal(num) => {
+                bits.push_bits(4, 3);
+                let groups = (1..16).rev().find(|i| num >> (4 * i) > 0).unwrap_or(0);
+                for i in (0..=groups).rev() {
+                    bits.push_bits((i > 0) as u64, 1);
+                    bits.push_bits(num >> (4 * i) & 0xf, 4);
+                }
             }
-            '1' => {
-                // length_id = 1
-                let total_num = input[1..12].iter().collect::<String>();
-                let total_num = usize::from_str_radix(&total_num, 2).unwrap();
-                let inp = &input[12..];
-                for _ in 0..total_num {
-                    elements.push(Packet::from(&inp[read..]));
-                    read += elements.last().unwrap().bits;
+            Value::Expression(op, elements) => {
+                match (op, elements.len()) {
+                    (_, 0) => return make_err!("{:?} has no operands", op),
+                    (Op::Greater | Op::Less | Op::Equal, n) if n != 2 => {
+                        return make_err!("{:?} expects 2 operands, found {}", op, n);
+                    }
+                    _ => {}
                 }
+                bits.push_bits(u8::from(*op) as u64, 3);
 
-                read += 18;
+                let mut sub = BitWriter::new();
+                for el in elements {
+                    el.encode(&mut sub, prefer)?;
+                }
+                let fits_bits = sub.len() < 1 << 15;
+                let fits_count = elements.len() < 1 << 11;
+                match (prefer, fits_bits, fits_count) {
+                    (LengthType::TotalBits, true, _) | (_, true, false) => {
+                        bits.push_bits(0, 1);
+                        bits.push_bits(sub.len() as u64, 15);
+                    }
+                    (_, _, true) => {
+                        bits.push_bits(1, 1);
+                        bits.push_bits(elements.len() as u64, 11);
+                    }
+                    _ => return make_err!("{:?} has too many operands", op),
+                }
+                bits.append(&sub);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes the packet as a hex transmission.
+    #[cfg(test)]
+    fn to_hex(&self, prefer: LengthType) -> Result<String, aoc::Error> {
+        let mut bits = BitWriter::new();
+        self.encode(&mut bits, prefer)?;
+        Ok(bits.to_hex())
+    }
+
+    /// Evaluates the expression, or returns `Err` if it overflows
+    /// or has no operands.
+    fn evaluate(&self) -> Result<u64, aoc::Error> {
+        let (op, elements) = match &self.value {
+            Value::Literal(num) => return Ok(*num),
+            Value::Expression(op, elements) => (op, elements),
+        };
+        let evaled = elements
+            .iter()
+            .map(|el| el.evaluate())
+            .collect::<Result<Vec<_>, _>>()?;
+        if evaled.is_empty() {
+            return make_err!("{} has no operands", op.name());
+        }
+
+        let checked = |f: fn(u64, u64) -> Option<u64>| {
+            evaled[1..]
+                .iter()
+                .try_fold(evaled[0], |acc, &el| match f(acc, el) {
+                    Some(res) => Ok(res),
+                    None => make_err!(
+                        "overflow in {}: {} {} {}",
+                        op.name(),
+                        acc,
+                        op.infix().unwrap().0,
+                        el
+                    ),
+                })
+        };
+        let res = match op {
+            Op::Sum => checked(u64::checked_add)?,
+            Op::Product => checked(u64::checked_mul)?,
+            Op::Min => *evaled.iter().min().unwrap(),
+            Op::Max => *evaled.iter().max().unwrap(),
+            Op::Greater => evaled.iter().tuple_windows().all(|(a, b)| a > b) as u64,
+            Op::Less => evaled.iter().tuple_windows().all(|(a, b)| a < b) as u64,
+            Op::Equal => evaled.iter().all_equal() as u64,
+        };
+        Ok(res)
+    }
+
+    /// Writes the expression in infix notation, with its operands in parentheses
+    /// if they bind weaker than `parent`.
+    fn write_infix(&self, f: &mut fmt::Formatter<'_>, parent: u8) -> fmt::Result {
+        let (op, elements) = match &self.value {
+            Value::Literal(num) => return write!(f, "{}", num),
+            Value::Expression(op, elements) => (op, elements),
+        };
+
+        match op.infix() {
+            Some((symbol, prec)) if elements.len() > 1 => {
+                if prec <= parent {
+                    write!(f, "(")?;
+                }
+                for (i, el) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {} ", symbol)?;
+                    }
+                    el.write_infix(f, prec)?;
+                }
+                if prec <= parent {
+                    write!(f, ")")?;
+                }
+                Ok(())
             }
             _ => {
-                unreachable!()
+                write!(f, "{}(", op.name())?;
+                for (i, el) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    el.write_infix(f, 0)?;
+                }
+                write!(f, ")")
             }
         }
-        (elements, read)
     }
+}
 
-    fn evaluate(&self) -> u64 {
+/// Formats the packet as an infix expression, like `max(1, 2 * 3)`,
+/// or with `{:#}` as an S-expression, like `(max 1 (product 2 3))`.
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return self.write_infix(f, 0);
+        }
         match &self.value {
-            Value::Literal(num) => *num,
+            Value::Literal(num) => write!(f, "{}", num),
             Value::Expression(op, elements) => {
-                let evaled = elements.iter().map(|el| el.evaluate());
-                match op {
-                    Op::Sum => evaled.sum(),
-                    Op::Product => evaled.product(),
-                    Op::Min => evaled.min().unwrap(),
-                    Op::Max => evaled.max().unwrap(),
-                    Op::Greater => evaled.tuple_windows().all(|(a, b)| a > b) as u64,
-                    Op::Less => evaled.tuple_windows().all(|(a, b)| a < b) as u64,
-                    Op::Equal => evaled.into_iter().all_equal() as u64,
+                write!(f, "({}", op.name())?;
+                for el in elements {
+                    write!(f, " {:#}", el)?;
                 }
+                write!(f, ")")
             }
         }
     }
 }
 
-impl From<&[char]> for Packet {
-    fn from(input: &[char]) -> Self {
-        let version = {
-            let v = input[0..3].iter().collect::<String>();
-            u64::from_str_radix(&v, 2).unwrap()
-        };
-        let packet_id = {
-            let v = input[3..6].iter().collect::<String>();
-            u8::from_str_radix(&v, 2).unwrap()
-        };
+/// Compiles the S-expression, like `(max 1 (product 2 3))`, into a packet.
+/// All the packets get version 0.
+impl FromStr for Packet {
+    type Err = aoc::Error;
 
-        match packet_id {
-            4 => {
-                let (value, bits) = Packet::parse_literal_parts(input);
-                Self {
-                    version,
-                    bits,
-                    value: Value::Literal(value),
-                }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = tokenize(s).into_iter().peekable();
+        let packet = parse_sexpr(&mut tokens)?;
+        match tokens.next() {
+            Some((col, token)) => make_err!("column {}: unexpected `{}`", col, token),
+            None => Ok(packet),
+        }
+    }
+}
+
+/// Tokens of the S-expression, along with their columns.
+type Tokens<'a> = Peekable<std::vec::IntoIter<(usize, &'a str)>>;
+
+fn tokenize(s: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        if c == '(' || c == ')' || c.is_whitespace() {
+            if let Some(st) = start.take() {
+                tokens.push((st + 1, &s[st..i]));
             }
-            pid => {
-                let op = Op::try_from(pid).unwrap();
-                let (elements, bits) = Packet::parse_operator_parts(input);
-                Self {
-                    version,
-                    bits,
-                    value: Value::Expression(op, elements),
-                }
+            if !c.is_whitespace() {
+                tokens.push((i + 1, &s[i..i + 1]));
             }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(st) = start {
+        tokens.push((st + 1, &s[st..]));
+    }
+    tokens
+}
+
+fn parse_sexpr(tokens: &mut Tokens) -> Result<Packet, aoc::Error> {
+    let (col, token) = match tokens.next() {
+        Some(t) => t,
+        None => return make_err!("unexpected end of expression"),
+    };
+    if token != "(" {
+        return match token.parse() {
+            Ok(num) => Ok(Packet::literal(0, num)),
+            Err(_) => {
+                make_err!("column {}: expected number or `(`, found `{}`", col, token)
+            }
+        };
+    }
+
+    let op: Op = match tokens.next() {
+        Some((op_col, name)) => name
+            .parse()
+            .map_err(|err: aoc::Error| err.context(format!("column {}", op_col)))?,
+        None => return make_err!("unexpected end of expression"),
+    };
+    let mut elements = vec![];
+    loop {
+        match tokens.peek() {
+            Some((_, ")")) => break,
+            Some(_) => elements.push(parse_sexpr(tokens)?),
+            None => return make_err!("column {}: unclosed `(`", col),
+        }
+    }
+    tokens.next();
+
+    match (op, elements.len()) {
+        (Op::Greater | Op::Less | Op::Equal, n) if n != 2 => {
+            make_err!(
+                "column {}: {} expects 2 operands, found {}",
+                col,
+                op.name(),
+                n
+            )
         }
+        (_, 0) => make_err!("column {}: {} has no operands", col, op.name()),
+        _ => Ok(Packet::expression(0, op, elements)),
+    }
+}
+
+/// Headers of the decoded packets, one per line.
+#[derive(Debug, Default)]
+struct Listing {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Listing {
+    fn add(&mut self, offset: usize, version: u64, type_id: u8, desc: String) {
+        self.lines.push(format!(
+            "{:>5}  {}v{} t{} {}",
+            offset,
+            "  ".repeat(self.depth),
+            version,
+            type_id,
+            desc
+        ));
     }
 }
 
@@ -168,34 +613,25 @@ fn add_versions(p: &Packet) -> u64 {
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, input: Lines) -> Vec<char> {
-        let msg = input.last().unwrap();
-        let msg = msg
-            .chars()
-            .map(|c| c.to_digit(16).unwrap())
-            .map(|n| format!("{:04b}", n))
-            .collect::<Vec<_>>()
-            .join("");
-
-        msg.chars().collect()
+    fn parse_input(&self, input: Input) -> Packet {
+        Packet::from_hex(input.last().unwrap()).unwrap()
     }
 }
 
 impl Solution for Solver {
     type Output = u64;
 
-    fn solve_a(&self, input: Lines) -> u64 {
-        let msg = self.parse_input(input);
-        let packet = Packet::from(&*msg);
-
+    fn solve_a(&self, input: Input) -> u64 {
+        let packet = self.parse_input(input);
         add_versions(&packet)
     }
 
-    fn solve_b(&self, input: Lines) -> u64 {
-        let msg = self.parse_input(input);
-        let packet = Packet::from(&*msg);
-
-        packet.evaluate()
+    fn solve_b(&self, input: Input) -> u64 {
+        let packet = self.parse_input(input.clone());
+        packet.evaluate().unwrap_or_else(|err| {
+            let listing = Packet::disassemble(input.last().unwrap()).unwrap();
+            panic!("{} in {}\n{}", err, packet, listing)
+        })
     }
 }
 
@@ -213,6 +649,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use adventofcode::{util::random::Lcg, StripMargin};
 
     #[test]
     fn test_solution_a() {
@@ -226,7 +663,7 @@ mod tests {
         ];
 
         test_data.iter().for_each(|&(input, ans)| {
-            assert_eq!(solver.solve_a(input.lines()), ans);
+            assert_eq!(solver.solve_a(Input::new(input)), ans);
         });
     }
 
@@ -245,7 +682,282 @@ mod tests {
         ];
 
         test_data.iter().for_each(|&(input, ans)| {
-            assert_eq!(solver.solve_b(input.lines()), ans);
+            assert_eq!(solver.solve_b(Input::new(input)), ans);
         });
     }
+
+    #[test]
+    fn test_encode() {
+        let packet = Packet::literal(6, 2021);
+        assert_eq!(packet.to_hex(LengthType::SubPackets).unwrap(), "D2FE28");
+
+        let packet = Packet::expression(
+            1,
+            Op::Less,
+            vec![Packet::literal(6, 10), Packet::literal(2, 20)],
+        );
+        assert_eq!(
+            packet.to_hex(LengthType::TotalBits).unwrap(),
+            "38006F45291200"
+        );
+
+        let packet = Packet::expression(
+            7,
+            Op::Max,
+            vec![
+                Packet::literal(2, 1),
+                Packet::literal(4, 2),
+                Packet::literal(1, 3),
+            ],
+        );
+        assert_eq!(
+            packet.to_hex(LengthType::SubPackets).unwrap(),
+            "EE00D40C823060"
+        );
+
+        let err = Packet::expression(0, Op::Equal, vec![Packet::literal(0, 1)])
+            .to_hex(LengthType::SubPackets)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Error: Equal expects 2 operands, found 1");
+        assert!(Packet::literal(8, 0).to_hex(LengthType::TotalBits).is_err());
+    }
+
+    /// Generates a random valid packet tree.
+    fn random_packet(rng: &mut Lcg, depth: usize) -> Packet {
+        let mut next = |n: u64| rng.below(n);
+
+        let version = next(8);
+        let kind = if depth == 0 { 4 } else { next(8) };
+        if kind == 4 {
+            let value = match next(3) {
+                0 => next(16),
+                1 => next(1 << 31) << 33 | next(1 << 31),
+                _ => next(1 << 20),
+            };
+            return Packet::literal(version, value);
+        }
+
+        let op = Op::try_from(kind as u8).unwrap();
+        let count = match op {
+            Op::Greater | Op::Less | Op::Equal => 2,
+            _ => 1 + next(4) as usize,
+        };
+        let elements = (0..count).map(|_| random_packet(rng, depth - 1)).collect();
+        Packet::expression(version, op, elements)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Lcg::new(2021);
+        for _ in 0..200 {
+            let packet = random_packet(&mut rng, 4);
+            for lt in [LengthType::TotalBits, LengthType::SubPackets] {
+                let hex = packet.to_hex(lt).unwrap();
+                let decoded = Packet::from_hex(&hex).unwrap();
+
+                assert_eq!(decoded, packet);
+                assert_eq!(decoded.to_hex(lt).unwrap(), hex);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_reader() {
+        let (bytes, len) = parse_hex("D2FE28A").unwrap();
+        assert_eq!(bytes, vec![0xD2, 0xFE, 0x28, 0xA0]);
+        assert_eq!(len, 28);
+
+        let mut reader = BitReader::new(&bytes, len);
+        assert_eq!(reader.read_bits(3).unwrap(), 6);
+        assert_eq!(reader.read_bits(10).unwrap(), 0b1001011111);
+        assert!(reader.read_bit().unwrap());
+        assert_eq!(reader.position(), 14);
+        assert_eq!(reader.read_bits(14).unwrap(), 0b10001010001010);
+
+        let err = reader.read_bits(1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: bit 28: expected 1 more bits, found 0"
+        );
+
+        let err = parse_hex("D2FG").unwrap_err();
+        assert_eq!(err.to_string(), "Error: column 4: unexpected 'G'");
+    }
+
+    #[test]
+    fn test_malformed_packets() {
+        let err = Packet::from_hex("D2FE").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: bit 16: expected 1 more bits, found 0"
+        );
+
+        let err = Packet::from_hex("EE00D40C8230").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: bit 47: expected 4 more bits, found 1"
+        );
+
+        // sum of the sub-packets in 5 bits, but the literal takes 11
+        let mut bits = BitWriter::new();
+        bits.push_bits(0, 7);
+        bits.push_bits(5, 15);
+        Packet::literal(0, 1)
+            .encode(&mut bits, LengthType::TotalBits)
+            .unwrap();
+        let err = Packet::from_hex(&bits.to_hex()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: bit 0: sub-packets overrun their length of 5 bits"
+        );
+
+        let mut bits = BitWriter::new();
+        bits.push_bits(4, 6);
+        (0..17).for_each(|_| bits.push_bits(0b11111, 5));
+        let err = Packet::from_hex(&bits.to_hex()).unwrap_err();
+        assert_eq!(err.to_string(), "Error: bit 0: literal overflows u64");
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let listing = Packet::disassemble("38006F45291200").unwrap();
+        assert_eq!(
+            listing,
+            r"
+            |    0  v1 t6 lt, length type 0: 27 bits
+            |   22    v6 t4 literal 10
+            |   33    v2 t4 literal 20
+            "
+            .trim_start_matches('\n')
+            .trim_end()
+            .strip_margin()
+        );
+
+        let listing = Packet::disassemble("8A004A801A8002F478").unwrap();
+        assert_eq!(
+            listing.lines().nth(3).unwrap(),
+            "   58        v6 t4 literal 15"
+        );
+        assert!(Packet::disassemble("8A004A801A").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        use Op::*;
+
+        let lit = |v| Packet::literal(0, v);
+        let expr = Packet::expression;
+
+        let packet = expr(0, Max, vec![lit(1), expr(0, Product, vec![lit(2), lit(3)])]);
+        assert_eq!(packet.to_string(), "max(1, 2 * 3)");
+        assert_eq!(format!("{:#}", packet), "(max 1 (product 2 3))");
+
+        let packet = expr(
+            0,
+            Product,
+            vec![
+                expr(0, Sum, vec![lit(1), lit(2)]),
+                lit(3),
+                expr(0, Sum, vec![lit(4)]),
+            ],
+        );
+        assert_eq!(packet.to_string(), "(1 + 2) * 3 * sum(4)");
+
+        let packet = expr(
+            0,
+            Less,
+            vec![
+                expr(0, Sum, vec![lit(1), expr(0, Sum, vec![lit(2), lit(3)])]),
+                expr(0, Equal, vec![lit(4), lit(5)]),
+            ],
+        );
+        assert_eq!(packet.to_string(), "1 + (2 + 3) < (4 == 5)");
+        assert_eq!(format!("{:#}", packet), "(lt (sum 1 (sum 2 3)) (eq 4 5))");
+
+        let packet = Packet::from_hex("9C0141080250320F1802104A08").unwrap();
+        assert_eq!(packet.to_string(), "1 + 3 == 2 * 2");
+    }
+
+    #[test]
+    fn test_overflow() {
+        let lit = |v| Packet::literal(0, v);
+
+        let packet =
+            Packet::expression(0, Op::Sum, vec![lit(u64::MAX - 1), lit(1), lit(1)]);
+        assert_eq!(
+            packet.evaluate().unwrap_err().to_string(),
+            "Error: overflow in sum: 18446744073709551615 + 1"
+        );
+
+        let packet =
+            Packet::expression(0, Op::Product, vec![lit(1 << 32), lit(1 << 32)]);
+        assert!(packet.evaluate().is_err());
+
+        let packet = Packet::expression(0, Op::Min, vec![]);
+        assert_eq!(
+            packet.evaluate().unwrap_err().to_string(),
+            "Error: min has no operands"
+        );
+    }
+
+    #[test]
+    fn test_compile() {
+        let solver = Solver {};
+        let test_data = [
+            ("42", 42),
+            ("(sum 1 2 3)", 6),
+            ("(product (sum 1 2) 3 (sum 4))", 36),
+            ("(min 7 (max 3 5) 6)", 5),
+            ("(gt (product 2 3) (max 4 5))", 1),
+            ("(lt 2 1)", 0),
+            ("(eq (sum 1 3) (product 2 2))", 1),
+            ("  (max\n 18446744073709551615 )", u64::MAX),
+        ];
+
+        for &(text, ans) in &test_data {
+            let packet: Packet = text.parse().unwrap();
+            let hex = packet.to_hex(LengthType::SubPackets).unwrap();
+            assert_eq!(solver.solve_b(Input::new(&hex)), ans, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let test_data = [
+            ("", "unexpected end of expression"),
+            ("(sum 1 2", "column 1: unclosed `(`"),
+            ("(sum 1 2))", "column 10: unexpected `)`"),
+            ("(sub 1 2)", "column 2: Unknown operator: sub"),
+            ("(max 1 x)", "column 8: expected number or `(`, found `x`"),
+            ("(sum 1 -2)", "column 8: expected number or `(`, found `-2`"),
+            (
+                "(max (eq 1 2 3))",
+                "column 6: eq expects 2 operands, found 3",
+            ),
+            ("(min)", "column 1: min has no operands"),
+            ("1 2", "column 3: unexpected `2`"),
+        ];
+
+        for &(text, msg) in &test_data {
+            let err = text.parse::<Packet>().unwrap_err();
+            assert_eq!(err.to_string(), format!("Error: {}", msg));
+        }
+    }
+
+    #[test]
+    fn test_compile_round_trip() {
+        let mut rng = Lcg::new(16);
+        for _ in 0..200 {
+            let packet = random_packet(&mut rng, 3);
+            let compiled: Packet = format!("{:#}", packet).parse().unwrap();
+            let hex = compiled.to_hex(LengthType::TotalBits).unwrap();
+            let decoded = Packet::from_hex(&hex).unwrap();
+
+            assert_eq!(format!("{:#}", decoded), format!("{:#}", packet));
+            match (packet.evaluate(), decoded.evaluate()) {
+                (Ok(a), Ok(b)) => assert_eq!(a, b),
+                (a, b) => assert_eq!(a.is_err(), b.is_err()),
+            }
+        }
+    }
 }
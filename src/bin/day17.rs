@@ -1,11 +1,10 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
+use adventofcode::{self as aoc, aoc_problem, Input, Solution};
 use itertools::Itertools;
-use std::str::Lines;
 
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, input: Lines) -> ((i32, i32), (i32, i32)) {
+    fn parse_input(&self, input: Input) -> ((i32, i32), (i32, i32)) {
         let coords = input
             .last()
             .unwrap()
@@ -43,7 +42,7 @@ fn simulate(
 impl Solution for Solver {
     type Output = i32;
 
-    fn solve_a(&self, input: Lines) -> i32 {
+    fn solve_a(&self, input: Input) -> i32 {
         let (_, (ymin, ymax)) = self.parse_input(input);
 
         assert!(ymin < 0);
@@ -53,7 +52,7 @@ impl Solution for Solver {
         vy * (1 + vy) / 2
     }
 
-    fn solve_b(&self, input: Lines) -> i32 {
+    fn solve_b(&self, input: Input) -> i32 {
         let ((xmin, xmax), (ymin, ymax)) = self.parse_input(input);
 
         assert!(ymin < 0);
@@ -97,10 +96,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 45);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 112);
     }
 }
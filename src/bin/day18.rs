@@ -1,113 +1,224 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
+use adventofcode::{self as aoc, aoc_problem, make_err, Input, Solution};
 use itertools::Itertools;
-use std::collections::{LinkedList, VecDeque};
-use std::fmt::Debug;
+use std::fmt;
+use std::iter::{Peekable, Sum};
 use std::ops::Add;
-use std::str::Lines;
+use std::str::{CharIndices, FromStr};
 
-#[derive(Clone, Debug)]
-struct SFNum {
-    value: LinkedList<(usize, u32)>,
+/// Snailfish number: either a regular number, or a pair of snailfish numbers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum SFNum {
+    Regular(u32),
+    Pair(Box<SFNum>, Box<SFNum>),
+}
+
+/// Pairs nested inside this many pairs explode.
+const MAX_DEPTH: usize = 4;
+
+/// Regular numbers of at least this value split.
+const MAX_REGULAR: u32 = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Action {
+    Addition,
+    Explode,
+    Split,
+}
+
+/// Snailfish number after one of the steps of the addition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Step {
+    action: Action,
+    number: SFNum,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            Action::Addition => "addition:",
+            Action::Explode => "explode:",
+            Action::Split => "split:",
+        };
+        write!(f, "after {:<9} {}", action, self.number)
+    }
 }
 
 impl Add for SFNum {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        let mut ll = LinkedList::new();
-        for part in [&self.value, &rhs.value] {
-            part.iter().for_each(|&(lvl, val)| {
-                ll.push_back((lvl + 1, val));
-            });
+        self.add_traced(rhs, None)
+    }
+}
+
+impl Sum for SFNum {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(Add::add)
+            .expect("Can't add up an empty list of snailfish numbers")
+    }
+}
+
+impl fmt::Display for SFNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SFNum::Regular(value) => write!(f, "{}", value),
+            SFNum::Pair(left, right) => write!(f, "[{},{}]", left, right),
         }
-        while Self::explode(&mut ll) || Self::split(&mut ll) {}
-        Self { value: ll }
     }
 }
 
-impl From<&str> for SFNum {
-    fn from(input: &str) -> Self {
-        let mut lvl = 0;
-        let mut list = LinkedList::new();
-        for c in input.chars() {
-            match c {
-                '[' => {
-                    lvl += 1;
-                }
-                ']' => {
-                    lvl -= 1;
-                }
-                ',' => {}
-                c => {
-                    list.push_back((lvl, c.to_digit(10).unwrap()));
-                }
-            }
+impl FromStr for SFNum {
+    type Err = aoc::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.char_indices().peekable();
+        let num = SFNum::parse(&mut chars)?;
+        if let SFNum::Regular(_) = num {
+            return make_err!("column 1: expected a pair, found a regular number");
+        }
+        match chars.next() {
+            None => Ok(num),
+            Some((col, c)) => make_err!("column {}: unexpected `{}`", col + 1, c),
         }
-        Self { value: list }
     }
 }
 
 impl SFNum {
-    fn magnitude(&self) -> u32 {
-        let mut queue = VecDeque::new();
+    fn pair(left: SFNum, right: SFNum) -> Self {
+        SFNum::Pair(Box::new(left), Box::new(right))
+    }
 
-        fn reduce(q: &mut VecDeque<(usize, u32)>) -> bool {
-            if q.len() < 2 {
-                return false;
+    fn parse(chars: &mut Peekable<CharIndices>) -> Result<Self, aoc::Error> {
+        match chars.next() {
+            None => make_err!("unexpected end of snailfish number"),
+            Some((_, '[')) => {
+                let left = SFNum::parse(chars)?;
+                SFNum::expect(chars, ',')?;
+                let right = SFNum::parse(chars)?;
+                SFNum::expect(chars, ']')?;
+                Ok(SFNum::pair(left, right))
             }
-            let (right, left) = (q.pop_back().unwrap(), q.pop_back().unwrap());
-            if left.0 == right.0 {
-                q.push_back((left.0 - 1, 3 * left.1 + 2 * right.1));
-                true
-            } else {
-                q.push_back(left);
-                q.push_back(right);
-                false
+            Some((col, c)) if c.is_ascii_digit() => {
+                let mut value = c.to_digit(10).unwrap();
+                while let Some(&(_, c)) = chars.peek() {
+                    let digit = match c.to_digit(10) {
+                        Some(digit) => digit,
+                        None => break,
+                    };
+                    value = match value
+                        .checked_mul(10)
+                        .and_then(|v| v.checked_add(digit))
+                    {
+                        Some(value) => value,
+                        None => {
+                            return make_err!("column {}: number is too large", col + 1)
+                        }
+                    };
+                    chars.next();
+                }
+                Ok(SFNum::Regular(value))
+            }
+            Some((col, c)) => {
+                make_err!("column {}: expected number or `[`, found `{}`", col + 1, c)
             }
         }
+    }
 
-        for &el in &self.value {
-            queue.push_back(el);
-            while reduce(&mut queue) {}
+    fn expect(
+        chars: &mut Peekable<CharIndices>,
+        expected: char,
+    ) -> Result<(), aoc::Error> {
+        match chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((col, c)) => {
+                make_err!("column {}: expected `{}`, found `{}`", col + 1, expected, c)
+            }
+            None => make_err!(
+                "unexpected end of snailfish number, expected `{}`",
+                expected
+            ),
         }
+    }
 
-        queue.pop_back().unwrap().1
+    /// Adds up two numbers, and records every step of the reduction in `trace`, if any.
+    fn add_traced(self, rhs: Self, mut trace: Option<&mut Vec<Step>>) -> Self {
+        let mut num = SFNum::pair(self, rhs);
+        let mut action = Action::Addition;
+        loop {
+            if let Some(trace) = trace.as_mut() {
+                trace.push(Step {
+                    action,
+                    number: num.clone(),
+                });
+            }
+            action = if num.explode(0).is_some() {
+                Action::Explode
+            } else if num.split() {
+                Action::Split
+            } else {
+                return num;
+            };
+        }
     }
 
-    fn split(list: &mut LinkedList<(usize, u32)>) -> bool {
-        let pos = list.iter().find_position(|&&(_, el)| el >= 10);
-        match pos {
-            None => false,
-            Some((pos, _)) => {
-                let mut split = list.split_off(pos);
-                let el = split.pop_front().unwrap();
-                list.push_back((el.0 + 1, el.1 / 2));
-                list.push_back((el.0 + 1, el.1 - el.1 / 2));
-                list.extend(split);
-                true
+    fn magnitude(&self) -> u32 {
+        match self {
+            SFNum::Regular(value) => *value,
+            SFNum::Pair(left, right) => 3 * left.magnitude() + 2 * right.magnitude(),
+        }
+    }
+
+    /// Explodes the leftmost pair nested too deep, and returns the values
+    /// that still need to be added to the neighbours on the left and on the right.
+    fn explode(&mut self, depth: usize) -> Option<(u32, u32)> {
+        let (left, right) = match self {
+            SFNum::Regular(_) => return None,
+            SFNum::Pair(left, right) => (left, right),
+        };
+
+        if depth >= MAX_DEPTH {
+            if let (SFNum::Regular(l), SFNum::Regular(r)) = (&**left, &**right) {
+                let carry = (*l, *r);
+                *self = SFNum::Regular(0);
+                return Some(carry);
             }
         }
+
+        if let Some((l, r)) = left.explode(depth + 1) {
+            right.add_leftmost(r);
+            return Some((l, 0));
+        }
+        if let Some((l, r)) = right.explode(depth + 1) {
+            left.add_rightmost(l);
+            return Some((0, r));
+        }
+        None
+    }
+
+    fn add_leftmost(&mut self, value: u32) {
+        match self {
+            SFNum::Regular(v) => *v += value,
+            SFNum::Pair(left, _) => left.add_leftmost(value),
+        }
     }
 
-    fn explode(list: &mut LinkedList<(usize, u32)>) -> bool {
-        let pos = list.iter().find_position(|&&(lvl, _)| lvl > 4);
-        match pos {
-            None => false,
-            Some((pos, _)) => {
-                let mut split = list.split_off(pos);
+    fn add_rightmost(&mut self, value: u32) {
+        match self {
+            SFNum::Regular(v) => *v += value,
+            SFNum::Pair(_, right) => right.add_rightmost(value),
+        }
+    }
 
-                let (left, right) =
-                    (split.pop_front().unwrap(), split.pop_front().unwrap());
-                if let Some(el) = list.pop_back() {
-                    list.push_back((el.0, el.1 + left.1));
-                }
-                list.push_back((left.0 - 1, 0));
-                if let Some(el) = split.pop_front() {
-                    split.push_front((el.0, el.1 + right.1));
-                }
-                list.extend(split);
+    /// Splits the leftmost regular number that is too large.
+    fn split(&mut self) -> bool {
+        match self {
+            SFNum::Regular(v) if *v >= MAX_REGULAR => {
+                *self =
+                    SFNum::pair(SFNum::Regular(*v / 2), SFNum::Regular(*v - *v / 2));
                 true
             }
+            SFNum::Regular(_) => false,
+            SFNum::Pair(left, right) => left.split() || right.split(),
         }
     }
 }
@@ -115,27 +226,20 @@ impl SFNum {
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, input: Lines) -> Vec<SFNum> {
-        input.map(SFNum::from).collect()
+    fn parse_input(&self, input: Input) -> Vec<SFNum> {
+        input.parse_lines().unwrap()
     }
 }
 
 impl Solution for Solver {
     type Output = u32;
 
-    fn solve_a(&self, input: Lines) -> u32 {
+    fn solve_a(&self, input: Input) -> u32 {
         let nums = self.parse_input(input);
-
-        let mut it = nums.into_iter();
-        let mut res = it.next().unwrap();
-        for n in it {
-            res = res + n;
-        }
-
-        res.magnitude()
+        nums.into_iter().sum::<SFNum>().magnitude()
     }
 
-    fn solve_b(&self, input: Lines) -> u32 {
+    fn solve_b(&self, input: Input) -> u32 {
         let nums = self.parse_input(input);
 
         (0..nums.len())
@@ -181,10 +285,107 @@ mod tests {
         .trim()
         .strip_margin_of("> ");
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 4140);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 3993);
+
+        let sum: SFNum = solver.parse_input(Input::new(&input)).into_iter().sum();
+        assert_eq!(
+            sum.to_string(),
+            "[[[[6,6],[7,6]],[[7,7],[7,0]]],[[[7,7],[7,7]],[[7,8],[9,9]]]]"
+        );
+    }
+
+    #[test]
+    fn test_sum() {
+        let input = r"
+            > [[[0,[4,5]],[0,0]],[[[4,5],[2,6]],[9,5]]]
+            > [7,[[[3,7],[4,3]],[[6,3],[8,8]]]]
+            > [[2,[[0,8],[3,4]]],[[[6,7],1],[7,[1,6]]]]
+            > [[[[2,4],7],[6,[0,5]]],[[[6,8],[2,8]],[[2,1],[4,5]]]]
+            > [7,[5,[[3,8],[1,4]]]]
+            > [[2,[2,2]],[8,[8,1]]]
+            > [2,9]
+            > [1,[[[9,3],9],[[9,0],[0,7]]]]
+            > [[[5,[7,4]],7],1]
+            > [[[[4,2],2],6],[8,7]]
+        "
+        .trim()
+        .strip_margin_of("> ");
+
+        let nums = Solver {}.parse_input(Input::new(&input));
+        let sum: SFNum = nums.into_iter().sum();
+        assert_eq!(
+            sum,
+            "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(sum.magnitude(), 3488);
+
+        let nums: Vec<SFNum> =
+            (1..=6).map(|k| SFNum::pair(k.into(), k.into())).collect();
+        assert_eq!(
+            nums.into_iter().sum::<SFNum>().to_string(),
+            "[[[[5,0],[7,4]],[5,5]],[6,6]]"
+        );
+    }
+
+    impl From<u32> for SFNum {
+        fn from(value: u32) -> Self {
+            SFNum::Regular(value)
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let a: SFNum = "[[[[4,3],4],4],[7,[[8,4],9]]]".parse().unwrap();
+        let b: SFNum = "[1,1]".parse().unwrap();
+
+        let mut trace = vec![];
+        let sum = a.add_traced(b, Some(&mut trace));
+        let trace = trace.iter().map(|step| step.to_string()).join("\n");
+
+        let expected = r"
+            |after addition: [[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]
+            |after explode:  [[[[0,7],4],[7,[[8,4],9]]],[1,1]]
+            |after explode:  [[[[0,7],4],[15,[0,13]]],[1,1]]
+            |after split:    [[[[0,7],4],[[7,8],[0,13]]],[1,1]]
+            |after split:    [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]
+            |after explode:  [[[[0,7],4],[[7,8],[6,0]]],[8,1]]
+        "
+        .trim()
+        .strip_margin();
+        assert_eq!(trace, expected);
+        assert_eq!(sum.to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+    }
+
+    #[test]
+    fn test_parse() {
+        let num: SFNum = "[[12,3],[456,[7,89]]]".parse().unwrap();
+        assert_eq!(num.to_string(), "[[12,3],[456,[7,89]]]");
+        assert_eq!(
+            num,
+            SFNum::pair(
+                SFNum::pair(12.into(), 3.into()),
+                SFNum::pair(456.into(), SFNum::pair(7.into(), 89.into()))
+            )
+        );
+
+        for (input, err) in [
+            ("", "unexpected end of snailfish number"),
+            ("[1,2", "unexpected end of snailfish number, expected `]`"),
+            ("[1;2]", "column 3: expected `,`, found `;`"),
+            ("[1,[x,2]]", "column 5: expected number or `[`, found `x`"),
+            ("[1,2]]", "column 6: unexpected `]`"),
+            ("[1,2,3]", "column 5: expected `]`, found `,`"),
+            ("42", "column 1: expected a pair, found a regular number"),
+            ("[1,99999999999]", "column 4: number is too large"),
+        ] {
+            let actual = input.parse::<SFNum>().unwrap_err();
+            assert_eq!(actual.to_string(), format!("Error: {}", err), "{}", input);
+        }
     }
 }
@@ -1,10 +1,13 @@
-use adventofcode::{self as aoc, aoc_problem, set, Solution};
+use adventofcode::util::pointcloud::PointCloud;
+use adventofcode::{
+    self as aoc, aoc_problem, make_err, map, util::problem::Part, Input, Solution,
+};
 use itertools::Itertools;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::ops::Sub;
-use std::str::Lines;
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 struct Coord {
     x: i32,
     y: i32,
@@ -23,7 +26,32 @@ impl Sub for Coord {
     }
 }
 
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.x, self.y, self.z)
+    }
+}
+
+/// Rotation-invariant key of the vector between two beacons:
+/// the absolute values of its components, sorted.
+type Fingerprint = [i32; 3];
+
+/// Scanners are aligned if they detect at least this many common beacons.
+const OVERLAP: usize = 12;
+
+/// Scanners detect all the beacons up to this far along each axis.
+const RANGE: i32 = 1000;
+
+const ORIGIN: Coord = Coord { x: 0, y: 0, z: 0 };
+
 impl Coord {
+    fn fingerprint(&self, other: &Self) -> Fingerprint {
+        let d = *other - *self;
+        let mut fp = [d.x.abs(), d.y.abs(), d.z.abs()];
+        fp.sort_unstable();
+        fp
+    }
+
     fn dist(&self, other: &Self) -> (i32, i32) {
         (
             (self.x - other.x).abs()
@@ -35,6 +63,14 @@ impl Coord {
         )
     }
 
+    fn within(&self, range: i32) -> bool {
+        self.x.abs() <= range && self.y.abs() <= range && self.z.abs() <= range
+    }
+
+    fn to_point(self) -> [i64; 3] {
+        [self.x as i64, self.y as i64, self.z as i64]
+    }
+
     fn rotate(&self, how: usize) -> Coord {
         let Coord { x, y, z } = *self;
         let rotations = [
@@ -79,9 +115,34 @@ impl Coord {
 #[derive(Debug)]
 struct Scanner {
     beacons: Vec<Coord>,
+    /// Pairs of beacons, keyed by the fingerprints of the vectors between them.
+    fingerprints: HashMap<Fingerprint, Vec<(usize, usize)>>,
 }
 
 impl Scanner {
+    fn new(beacons: Vec<Coord>) -> Self {
+        let mut fingerprints: HashMap<_, Vec<_>> = map![];
+        for (i, j) in (0..beacons.len()).tuple_combinations() {
+            let fp = beacons[i].fingerprint(&beacons[j]);
+            fingerprints.entry(fp).or_default().push((i, j));
+        }
+        Self {
+            beacons,
+            fingerprints,
+        }
+    }
+
+    /// Counts the pairs of beacons whose fingerprints the other scanner also has.
+    /// Scanners with `n` common beacons have at least `n * (n - 1) / 2` of them.
+    fn common_pairs(&self, other: &Scanner) -> usize {
+        self.fingerprints
+            .iter()
+            .filter_map(|(fp, pairs)| {
+                other.fingerprints.get(fp).map(|o| pairs.len().min(o.len()))
+            })
+            .sum()
+    }
+
     fn rotate_and_translate_points(&mut self, how: usize, ddd: Coord) {
         self.beacons.iter_mut().for_each(|b| {
             *b = b.rotate(how) - ddd;
@@ -89,93 +150,197 @@ impl Scanner {
     }
 }
 
+/// Position and orientation of a scanner in the map.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Placement {
+    /// Position relative to the first scanner.
+    position: Coord,
+    /// Index of the rotation that turns the reports of the scanner
+    /// into the axes of the first scanner.
+    rotation: usize,
+}
+
+/// Map assembled from the reports of all the scanners.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct BeaconMap {
+    /// Placements of the scanners, in the order of their reports.
+    scanners: Vec<Placement>,
+    /// Distinct beacons, sorted.
+    beacons: Vec<Coord>,
+}
+
+impl From<&BeaconMap> for PointCloud {
+    fn from(map: &BeaconMap) -> Self {
+        let mut cloud = PointCloud::new();
+        let scanners = map.scanners.iter().map(|s| s.position.to_point());
+        cloud.add_layer("scanner", [255, 0, 0], scanners);
+        let beacons = map.beacons.iter().map(|b| b.to_point());
+        cloud.add_layer("beacon", [255, 255, 255], beacons);
+        cloud
+    }
+}
+
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, mut input: Lines) -> Vec<Scanner> {
-        let mut scanners = vec![];
-        while let Some(_) = input.next() {
-            let mut beacons = vec![];
-            for line in input.by_ref() {
-                if line.is_empty() {
-                    break;
-                }
-                let p: Vec<_> = line
-                    .split(',')
-                    .map(|el| el.parse::<i32>().unwrap())
+    fn parse_input(&self, input: Input) -> Vec<Scanner> {
+        input
+            .sections()
+            .map(|section| {
+                let beacons = section
+                    .skip(1)
+                    .map(|line| {
+                        let p: Vec<_> = line
+                            .split(',')
+                            .map(|el| el.parse::<i32>().unwrap())
+                            .collect();
+                        Coord {
+                            x: p[0],
+                            y: p[1],
+                            z: p[2],
+                        }
+                    })
                     .collect();
-                beacons.push(Coord {
-                    x: p[0],
-                    y: p[1],
-                    z: p[2],
-                })
+                Scanner::new(beacons)
+            })
+            .collect()
+    }
+
+    /// Aligns the scanners that detect at least `overlap` common beacons,
+    /// and assembles the map. Returns `Err` if some scanners can't be aligned,
+    /// or if their reports contradict each other.
+    fn solve(
+        &self,
+        scanners: &mut [Scanner],
+        overlap: usize,
+    ) -> Result<BeaconMap, aoc::Error> {
+        let n = scanners.len();
+
+        // rank the candidates for every scanner before trying to align any of them
+        let mut candidates = vec![vec![]; n];
+        for (a, b) in (0..n).tuple_combinations() {
+            let common = scanners[a].common_pairs(&scanners[b]);
+            if common >= overlap * overlap.saturating_sub(1) / 2 {
+                candidates[a].push((common, b));
+                candidates[b].push((common, a));
             }
-            scanners.push(Scanner { beacons })
         }
-        scanners
-    }
+        candidates
+            .iter_mut()
+            .for_each(|c| c.sort_unstable_by(|x, y| y.cmp(x)));
 
-    fn solve(&self, scanners: &mut Vec<Scanner>) -> Vec<(usize, Coord)> {
-        let mut seen = set![];
+        let mut placements = vec![None; n];
+        placements[0] = Some(Placement {
+            position: ORIGIN,
+            rotation: 0,
+        });
         let mut queue = VecDeque::from([0]);
-        let mut abs_positions = vec![(0, Coord { x: 0, y: 0, z: 0 })];
 
-        while seen.len() < scanners.len() {
-            let left = queue.pop_front().unwrap();
-            seen.insert(left);
-            for right in 0..scanners.len() {
-                if seen.contains(&right) {
+        while let Some(left) = queue.pop_front() {
+            for &(_, right) in &candidates[left] {
+                if placements[right].is_some() {
                     continue;
                 }
                 if let Some((how, dxdydz)) =
-                    try_align(&scanners[left], &scanners[right])
+                    try_align(&scanners[left], &scanners[right], overlap)
                 {
                     scanners[right].rotate_and_translate_points(how, dxdydz);
+                    placements[right] = Some(Placement {
+                        position: ORIGIN - dxdydz,
+                        rotation: how,
+                    });
                     queue.push_back(right);
-                    abs_positions.push((right, dxdydz));
                 }
             }
         }
-        abs_positions
+        let unaligned = placements.iter().positions(Option::is_none).join(", ");
+        if !unaligned.is_empty() {
+            return make_err!("Scanners that don't overlap the others: {}", unaligned);
+        }
+        let placements: Vec<_> = placements.into_iter().flatten().collect();
+        validate(scanners, &placements)?;
+
+        let beacons = scanners
+            .iter()
+            .flat_map(|s| s.beacons.iter().copied())
+            .collect::<HashSet<_>>();
+        let mut beacons = beacons.into_iter().collect::<Vec<_>>();
+        beacons.sort_unstable();
+
+        Ok(BeaconMap {
+            scanners: placements,
+            beacons,
+        })
     }
 }
 
-fn try_align(s1: &Scanner, s2: &Scanner) -> Option<(usize, Coord)> {
-    let mut pts1 = set![];
-    let mut pts2 = set![];
-    let mut common = vec![];
-    for i1 in 0..s1.beacons.len() {
-        for j1 in i1 + 1..s1.beacons.len() {
-            for i2 in 0..s2.beacons.len() {
-                for j2 in i2 + 1..s2.beacons.len() {
-                    if s1.beacons[i1].dist(&s1.beacons[j1])
-                        == s2.beacons[i2].dist(&s2.beacons[j2])
-                    {
-                        pts1.insert(i1);
-                        pts1.insert(j1);
-                        pts2.insert(i2);
-                        pts2.insert(j2);
-                        common.push((i1, j1, i2, j2));
-                    }
+/// Checks that every scanner detects all the beacons which the other scanners
+/// report within its range, once the reports are aligned.
+fn validate(scanners: &[Scanner], placements: &[Placement]) -> Result<(), aoc::Error> {
+    let detected: Vec<HashSet<_>> = scanners
+        .iter()
+        .map(|s| s.beacons.iter().copied().collect())
+        .collect();
+
+    let mut conflicts = vec![];
+    for (a, b) in (0..scanners.len()).tuple_combinations() {
+        let (pa, pb) = (placements[a].position, placements[b].position);
+        if !(pa - pb).within(2 * RANGE) {
+            continue;
+        }
+        for (this, other, pos) in [(a, b, pa), (b, a, pb)] {
+            for &beacon in &scanners[other].beacons {
+                if (beacon - pos).within(RANGE) && !detected[this].contains(&beacon) {
+                    conflicts.push(format!(
+                        "scanner {} reports beacon {} in the range of scanner {}, \
+                        which doesn't detect it",
+                        other, beacon, this
+                    ));
                 }
             }
         }
     }
-    if pts1.len() < 12 || pts2.len() < 12 {
-        return None;
+
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        make_err!("Inconsistent reports: {}", conflicts.join("; "))
     }
+}
+
+/// Finds the rotation and translation that map the beacons of `s2` onto at least
+/// `overlap` beacons of `s1`, trying only the pairs with the same fingerprints.
+fn try_align(s1: &Scanner, s2: &Scanner, overlap: usize) -> Option<(usize, Coord)> {
+    let known: HashSet<_> = s1.beacons.iter().copied().collect();
 
-    let chosen = common[0];
-    for how in 0..24 {
-        let p0 = s1.beacons[chosen.0];
-        let p1 = s1.beacons[chosen.1];
-        let p2 = s2.beacons[chosen.2].rotate(how);
-        let p3 = s2.beacons[chosen.3].rotate(how);
-        if p0 - p1 == p2 - p3 {
-            return Some((how, p2 - p0));
+    for (fp, pairs1) in &s1.fingerprints {
+        // vectors with zero or repeated components fit more than one rotation
+        if fp[0] == 0 || fp[0] == fp[1] || fp[1] == fp[2] {
+            continue;
         }
-        if p0 - p1 == p3 - p2 {
-            return Some((how, p3 - p0));
+        let pairs2 = match s2.fingerprints.get(fp) {
+            Some(pairs) => pairs,
+            None => continue,
+        };
+
+        for (&(i1, j1), &(i2, j2)) in pairs1.iter().cartesian_product(pairs2) {
+            let target = s1.beacons[j1] - s1.beacons[i1];
+            for (a, b) in [(i2, j2), (j2, i2)] {
+                let v = s2.beacons[b] - s2.beacons[a];
+                let how = match (0..24).find(|&how| v.rotate(how) == target) {
+                    Some(how) => how,
+                    None => continue,
+                };
+                let dxdydz = s2.beacons[a].rotate(how) - s1.beacons[i1];
+                let common = s2
+                    .beacons
+                    .iter()
+                    .filter(|b| known.contains(&(b.rotate(how) - dxdydz)))
+                    .count();
+                if common >= overlap {
+                    return Some((how, dxdydz));
+                }
+            }
         }
     }
 
@@ -185,30 +350,30 @@ fn try_align(s1: &Scanner, s2: &Scanner) -> Option<(usize, Coord)> {
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         let mut scanners = self.parse_input(input);
+        let map = self.solve(&mut scanners, OVERLAP).unwrap();
 
-        let _ = self.solve(&mut scanners);
-
-        let all_points = scanners
-            .iter()
-            .flat_map(|s| s.beacons.iter().copied())
-            .collect::<HashSet<_>>();
-
-        all_points.len()
+        map.beacons.len()
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
         let mut scanners = self.parse_input(input);
+        let map = self.solve(&mut scanners, OVERLAP).unwrap();
 
-        let abs_positions = self.solve(&mut scanners);
-
-        abs_positions
+        map.scanners
             .iter()
-            .cartesian_product(abs_positions.iter())
-            .map(|((_, p1), (_, p2))| p1.dist(p2).0)
+            .tuple_combinations()
+            .map(|(s1, s2)| s1.position.dist(&s2.position).0)
             .max()
-            .unwrap() as usize
+            .unwrap_or(0) as usize
+    }
+
+    fn explain(&self, _part: Part, input: Input) -> Option<String> {
+        let mut scanners = self.parse_input(input);
+        let map = self.solve(&mut scanners, OVERLAP).unwrap();
+
+        Some(PointCloud::from(&map).to_ply())
     }
 }
 
@@ -226,12 +391,10 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use adventofcode::StripMargin;
+    use adventofcode::{util::random::Lcg, StripMargin};
 
-    #[test]
-    fn test_solution() {
-        let solver = Solver {};
-        let input = r"
+    fn example() -> String {
+        r"
             |--- scanner 0 ---
             |404,-588,-901
             |528,-643,409
@@ -370,12 +533,189 @@ mod tests {
             |30,-46,-14
         "
         .trim()
-        .strip_margin();
+        .strip_margin()
+    }
 
-        let ans = solver.solve_a(input.lines());
+    #[test]
+    fn test_solution() {
+        let solver = Solver {};
+        let input = example();
+
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 79);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 3621);
     }
+
+    /// Generates the reports of scanners placed along the x axis, 500 apart,
+    /// in random orientations, along with their positions and the detected beacons.
+    fn synthetic(scanners: i32, beacons: usize) -> (String, Vec<Coord>, Vec<Coord>) {
+        let mut rng = Lcg::new(19);
+        let mut next = |lo: i32, hi: i32| lo + rng.below((hi - lo) as u64) as i32;
+
+        let all: HashSet<_> = (0..beacons)
+            .map(|_| Coord {
+                x: next(-1000, 500 * scanners + 500),
+                y: next(-1000, 1000),
+                z: next(-1000, 1000),
+            })
+            .collect();
+
+        let mut positions = vec![];
+        let mut reports = vec![];
+        for k in 0..scanners {
+            let pos = Coord {
+                x: 500 * k,
+                y: next(-100, 100),
+                z: next(-100, 100),
+            };
+            // the first scanner defines the orientation of the map
+            let how = if k == 0 { 0 } else { next(0, 24) as usize };
+            let visible = all
+                .iter()
+                .map(|&b| b - pos)
+                .filter(|d| d.within(RANGE))
+                .map(|d| d.rotate(how))
+                .map(|d| format!("{},{},{}", d.x, d.y, d.z))
+                .join("\n");
+            reports.push(format!("--- scanner {} ---\n{}", k, visible));
+            positions.push(pos);
+        }
+        let origin = positions[0];
+        let all = all
+            .into_iter()
+            .filter(|&b| positions.iter().any(|&p| (b - p).within(RANGE)))
+            .map(|b| b - origin)
+            .sorted()
+            .collect();
+        (
+            reports.join("\n\n"),
+            positions.iter().map(|&p| p - origin).collect(),
+            all,
+        )
+    }
+
+    #[test]
+    fn test_synthetic() {
+        let solver = Solver {};
+        let (input, positions, beacons) = synthetic(30, 500);
+
+        let mut scanners = solver.parse_input(Input::new(&input));
+        let map = solver.solve(&mut scanners, OVERLAP).unwrap();
+        let found: Vec<_> = map.scanners.iter().map(|s| s.position).collect();
+        assert_eq!(found, positions);
+        assert_eq!(map.beacons, beacons);
+    }
+
+    #[test]
+    fn test_unaligned_scanners() {
+        let solver = Solver {};
+        let input = format!(
+            "{}\n\n--- scanner 5 ---\n1,2,3\n-40,50,-60\n700,-800,900\n",
+            example()
+        );
+        let mut scanners = solver.parse_input(Input::new(&input));
+        assert_eq!(
+            solver
+                .solve(&mut scanners, OVERLAP)
+                .unwrap_err()
+                .to_string(),
+            "Error: Scanners that don't overlap the others: 5"
+        );
+
+        // without scanner 1, scanner 0 is cut off from the rest
+        let mut scanners = solver.parse_input(Input::new(&example()));
+        scanners.remove(1);
+        let err = solver.solve(&mut scanners, OVERLAP).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Scanners that don't overlap the others: 1, 2, 3"
+        );
+    }
+
+    #[test]
+    fn test_inconsistent_reports() {
+        let solver = Solver {};
+        let (input, _, _) = synthetic(3, 500);
+
+        // scanner 1 misses one of the beacons detected by its neighbours
+        let mut lines: Vec<_> = input.lines().collect();
+        let first = lines
+            .iter()
+            .position(|&l| l == "--- scanner 1 ---")
+            .unwrap();
+        let missed = lines.remove(first + 1);
+        let input = lines.join("\n");
+
+        let mut scanners = solver.parse_input(Input::new(&input));
+        let err = solver
+            .solve(&mut scanners, OVERLAP)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("Error: Inconsistent reports: scanner "),
+            "{}",
+            err
+        );
+        assert!(
+            err.ends_with("in the range of scanner 1, which doesn't detect it"),
+            "{} (missed {})",
+            err,
+            missed
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let solver = Solver {};
+        let mut scanners = solver.parse_input(Input::new(&example()));
+
+        // the first two scanners have exactly 12 beacons in common
+        assert!(try_align(&scanners[0], &scanners[1], 13).is_none());
+        assert!(try_align(&scanners[0], &scanners[1], 12).is_some());
+
+        let map = solver.solve(&mut scanners, OVERLAP).unwrap();
+        let positions: Vec<_> = map
+            .scanners
+            .iter()
+            .map(|s| (s.position.x, s.position.y, s.position.z))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (0, 0, 0),
+                (68, -1246, -43),
+                (1105, -1205, 1229),
+                (-92, -2380, -20),
+                (-20, -1133, 1061)
+            ]
+        );
+        assert_eq!(map.scanners[0].rotation, 0);
+        assert_eq!(
+            map.beacons[..2],
+            [
+                Coord {
+                    x: -892,
+                    y: 524,
+                    z: 684
+                },
+                Coord {
+                    x: -876,
+                    y: 649,
+                    z: 763
+                }
+            ]
+        );
+
+        let cloud = PointCloud::from(&map);
+        assert_eq!(cloud.len(), 5 + 79);
+        let csv = cloud.to_csv();
+        assert_eq!(csv.lines().nth(2), Some("scanner,68,-1246,-43"));
+        assert_eq!(csv.lines().nth(6), Some("beacon,-892,524,684"));
+        let ply = cloud.to_ply();
+        assert!(ply.contains("element vertex 84\n"));
+        assert_eq!(ply.lines().last(), Some("1994 -1805 1792 255 255 255"));
+        assert_eq!(solver.explain(Part::B, Input::new(&example())), Some(ply));
+    }
 }
@@ -1,7 +1,9 @@
-use adventofcode::{self as aoc, aoc_problem, make_err, map, Solution};
+use adventofcode::{
+    self as aoc, aoc_problem, make_err, map, util::simulation::Simulation, Input,
+    Solution,
+};
 use itertools::Itertools;
 use std::collections::HashMap;
-use std::str::Lines;
 
 #[derive(Clone, Copy, Debug)]
 enum Pixel {
@@ -35,16 +37,19 @@ impl TryFrom<char> for Pixel {
 struct Solver;
 
 impl Solver {
-    fn parse_input(&self, mut input: Lines) -> (Vec<Pixel>, Image) {
-        let mut algo = vec![];
-        for line in input.by_ref() {
-            if line.is_empty() {
-                break;
-            }
-            algo.extend(line.chars().map(|c| Pixel::try_from(c).unwrap()));
-        }
-
-        let m: Image = input
+    fn parse_input(&self, input: Input) -> (Vec<Pixel>, Image) {
+        let mut sections = input.sections();
+
+        let algo = sections
+            .next()
+            .expect("Expect enhancement algorithm")
+            .flat_map(|line| line.chars())
+            .map(|c| Pixel::try_from(c).unwrap())
+            .collect();
+
+        let m: Image = sections
+            .next()
+            .expect("Expect input image")
             .enumerate()
             .flat_map(|(i, line)| {
                 line.chars().enumerate().map(move |(j, c)| {
@@ -60,60 +65,96 @@ impl Solver {
     }
 }
 
-fn transform(mut image: Image, algo: &[Pixel], steps: usize) -> Image {
-    assert_eq!(algo.len(), 512);
-    assert!(matches!(algo[0], Pixel::Dark) || matches!(algo[511], Pixel::Dark));
+/// Image being enhanced, along with the value of the infinitely many pixels
+/// outside of the tracked region.
+struct Enhancement<'a> {
+    algo: &'a [Pixel],
+    image: Image,
+    default: Pixel,
+    left: i32,
+    right: i32,
+}
 
-    let mut upd = map![];
+impl<'a> Enhancement<'a> {
+    fn new(image: Image, algo: &'a [Pixel]) -> Self {
+        assert_eq!(algo.len(), 512);
+        assert!(matches!(algo[0], Pixel::Dark) || matches!(algo[511], Pixel::Dark));
+
+        let right = image.iter().map(|(&(x, _), _)| x).max().unwrap();
+        Self {
+            algo,
+            image,
+            default: Pixel::Dark,
+            left: 0,
+            right,
+        }
+    }
+
+    fn count_lit(&self) -> usize {
+        self.image
+            .iter()
+            .filter(|&(_, &p)| matches!(p, Pixel::Light))
+            .count()
+    }
+}
 
-    let mut left = 0;
-    let mut right = image.iter().map(|(&(x, _), _)| x).max().unwrap();
+impl Simulation for Enhancement<'_> {
+    type State = (Vec<(i32, i32)>, usize);
 
-    for step in 0..steps {
-        let default = if step % 2 == 1 { algo[0] } else { Pixel::Dark };
+    fn step(&mut self) {
+        let mut upd = map![];
 
-        left -= 1;
-        right += 1;
+        self.left -= 1;
+        self.right += 1;
 
-        for x in left..=right {
-            for y in left..=right {
+        for x in self.left..=self.right {
+            for y in self.left..=self.right {
                 let idx = (-1..=1)
                     .cartesian_product(-1..=1)
                     .map(|(dx, dy)| {
-                        image.get(&(x + dx, y + dy)).unwrap_or(&default).value()
+                        self.image
+                            .get(&(x + dx, y + dy))
+                            .unwrap_or(&self.default)
+                            .value()
                     })
                     .zip(0..9)
                     .map(|(val, k)| 2_usize.pow(8 - k) * val)
                     .sum::<usize>();
-                upd.insert((x, y), algo.get(idx).copied().unwrap());
+                upd.insert((x, y), self.algo.get(idx).copied().unwrap());
             }
         }
-        image = upd;
-        upd = map![];
+
+        self.image = upd;
+        self.default = self.algo[511 * self.default.value()];
     }
 
-    image
+    fn state(&self) -> Self::State {
+        let lit = self
+            .image
+            .iter()
+            .filter(|&(_, &p)| matches!(p, Pixel::Light))
+            .map(|(&pos, _)| pos)
+            .sorted()
+            .collect();
+        (lit, self.default.value())
+    }
 }
 
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         let (algo, image) = self.parse_input(input);
-        let image = transform(image, &algo, 2);
-        image
-            .iter()
-            .filter(|&(_, &p)| matches!(p, Pixel::Light))
-            .count()
+        let mut enhancement = Enhancement::new(image, &algo);
+        enhancement.run(2);
+        enhancement.count_lit()
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
         let (algo, image) = self.parse_input(input);
-        let image = transform(image, &algo, 50);
-        image
-            .iter()
-            .filter(|&(_, &p)| matches!(p, Pixel::Light))
-            .count()
+        let mut enhancement = Enhancement::new(image, &algo);
+        enhancement.run(50);
+        enhancement.count_lit()
     }
 }
 
@@ -154,10 +195,10 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 35);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 3351);
     }
 }
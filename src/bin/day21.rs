@@ -1,11 +1,21 @@
-use adventofcode::{self as aoc, aoc_problem, map, Solution};
-use std::str::Lines;
+use adventofcode::util::memo::Memoized;
+use adventofcode::{
+    self as aoc, aoc_problem, make_err, util::problem::Part, DefaultMap, Input,
+    Solution,
+};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt;
 
-struct DeterministicDie(usize);
+/// Die that rolls 1, 2, 3, ... up to the number of its faces, and then starts over.
+struct DeterministicDie {
+    faces: usize,
+    last: usize,
+}
 
 impl DeterministicDie {
-    fn new() -> Self {
-        Self(100)
+    fn new(faces: usize) -> Self {
+        Self { faces, last: faces }
     }
 }
 
@@ -13,105 +23,325 @@ impl Iterator for DeterministicDie {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.0 = self.0 % 100 + 1;
-        Some(self.0)
+        self.last = self.last % self.faces + 1;
+        Some(self.last)
     }
 }
 
-struct Solver;
+/// Position and score of a player.
+type Player = (usize, usize);
 
-impl Solver {
-    fn parse_input(&self, input: Lines) -> (usize, usize) {
-        let pos: Vec<usize> = input
-            .map(|line| line.split_once(": ").expect("Expect one delimiter").1)
-            .map(|n| n.parse().unwrap())
-            .collect();
-
-        assert_eq!(pos.len(), 2);
+/// Rules of the game of Dice.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Rules {
+    players: usize,
+    /// Number of spaces on the circular board.
+    board: usize,
+    /// Number of faces of the die.
+    faces: usize,
+    /// Number of rolls of the die on each turn.
+    rolls: usize,
+    /// Score that wins the game.
+    target: usize,
+}
 
-        (pos[0], pos[1])
-    }
+/// Outcome of a game with a deterministic die.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Game {
+    winner: usize,
+    scores: Vec<usize>,
+    /// Total number of times the die was rolled.
+    rolls: usize,
 }
 
-impl Solution for Solver {
-    type Output = usize;
+const PRACTICE: Rules = Rules {
+    players: 2,
+    board: 10,
+    faces: 100,
+    rolls: 3,
+    target: 1000,
+};
+
+const DIRAC: Rules = Rules {
+    players: 2,
+    board: 10,
+    faces: 3,
+    rolls: 3,
+    target: 21,
+};
+
+impl Rules {
+    /// Checks that the game can be played from the given (1-based) starting positions,
+    /// and converts them into the initial states of the players.
+    fn start(&self, positions: &[usize]) -> Result<Vec<Player>, aoc::Error> {
+        if self.players == 0 || self.board == 0 || self.faces == 0 || self.rolls == 0 {
+            return make_err!("Game can't be played by {:?}", self);
+        }
+        let universes = u32::try_from(self.rolls)
+            .ok()
+            .and_then(|rolls| (self.faces as u64).checked_pow(rolls));
+        if universes.is_none() {
+            return make_err!("Universes split into too many on every turn");
+        }
+        if positions.len() != self.players {
+            return make_err!(
+                "Expected {} players, found {}",
+                self.players,
+                positions.len()
+            );
+        }
+        positions
+            .iter()
+            .enumerate()
+            .map(|(k, &pos)| {
+                if (1..=self.board).contains(&pos) {
+                    Ok((pos - 1, 0))
+                } else {
+                    make_err!("Player {} starts off the board at {}", k + 1, pos)
+                }
+            })
+            .collect()
+    }
 
-    fn solve_a(&self, input: Lines) -> usize {
-        let (pos1, pos2) = self.parse_input(input);
+    /// Moves the player by `steps` spaces, and returns `true` if they win.
+    fn advance(&self, player: &mut Player, steps: usize) -> bool {
+        player.0 = (player.0 + steps) % self.board;
+        player.1 += player.0 + 1;
+        player.1 >= self.target
+    }
 
-        const TARGET: usize = 1000;
-        let mut die = DeterministicDie::new();
+    /// Returns the sums of the rolls on one turn, along with the number of universes
+    /// in which each sum occurs.
+    fn outcomes(&self) -> Vec<(usize, u64)> {
+        let mut freq = vec![1_u64];
+        for _ in 0..self.rolls {
+            let mut next = vec![0; freq.len() + self.faces];
+            for (sum, &n) in freq.iter().enumerate() {
+                for face in 1..=self.faces {
+                    next[sum + face] += n;
+                }
+            }
+            freq = next;
+        }
+        (0..).zip(freq).filter(|&(_, n)| n > 0).collect()
+    }
 
-        let mut scores = [0, 0];
-        let mut positions = [pos1 - 1, pos2 - 1];
+    /// Plays the game with the deterministic die, until one of the players wins.
+    fn play(
+        &self,
+        positions: &[usize],
+        mut die: impl Iterator<Item = usize>,
+    ) -> Result<Game, aoc::Error> {
+        let mut players = self.start(positions)?;
+        let mut rolls = 0;
         for turn in 0.. {
-            let roll = (0..3).map(|_| die.next().unwrap()).sum::<usize>();
-            positions[turn % 2] = (positions[turn % 2] + roll) % 10;
-            scores[turn % 2] += positions[turn % 2] + 1;
-            if scores[turn % 2] >= TARGET {
-                let rounds = 3 * (turn + 1);
-                return rounds * scores[0].min(scores[1]);
+            let steps: usize = die.by_ref().take(self.rolls).sum();
+            rolls += self.rolls;
+            let current = turn % self.players;
+            if self.advance(&mut players[current], steps) {
+                return Ok(Game {
+                    winner: current,
+                    scores: players.iter().map(|p| p.1).collect(),
+                    rolls,
+                });
             }
         }
         unreachable!()
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
-        let (pos1, pos2) = self.parse_input(input);
-        const TARGET: usize = 21;
+    /// Returns the memoized function that counts the universes in which each
+    /// of the players wins the game with the Dirac die. The players are listed
+    /// starting from the one who is about to move, or `None` if the counts
+    /// don't fit into `u64`.
+    fn win_counter(&self) -> Memoized<'_, Vec<Player>, Option<Vec<u64>>> {
+        let outcomes = self.outcomes();
+        Memoized::new(
+            move |count: &mut Memoized<_, Option<Vec<u64>>>, players: Vec<Player>| {
+                let n = players.len();
+                let mut wins = vec![0_u64; n];
+                for &(steps, freq) in &outcomes {
+                    let mut current = players[0];
+                    if self.advance(&mut current, steps) {
+                        wins[0] = wins[0].checked_add(freq)?;
+                        continue;
+                    }
+                    let mut next = players[1..].to_vec();
+                    next.push(current);
+                    for (k, w) in count.call(next)?.into_iter().enumerate() {
+                        let won = &mut wins[(k + 1) % n];
+                        *won = won.checked_add(freq.checked_mul(w)?)?;
+                    }
+                }
+                Some(wins)
+            },
+        )
+    }
+
+    /// Counts the universes in which each of the players wins the game
+    /// with the Dirac die.
+    fn count_wins(&self, positions: &[usize]) -> Result<Vec<u64>, aoc::Error> {
+        let players = self.start(positions)?;
+        self.win_counter().call(players).ok_or_else(overflow)
+    }
 
-        let mut scores = map![0 => map![(pos1-1, pos2-1, 0, 0) => 1_usize]];
-        let mut final_scores = map![];
+    /// Counts the wins of both players for every pair of starting positions:
+    /// `table[i][j]` lists the wins when the players start at `i + 1` and `j + 1`.
+    fn win_table(&self) -> Result<Vec<Vec<Vec<u64>>>, aoc::Error> {
+        if self.players != 2 {
+            return make_err!("Win table needs 2 players, found {}", self.players);
+        }
+        self.start(&[1, 1])?;
 
-        const POSSIBILITIES: [(usize, usize); 7] =
-            [(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)];
+        let mut count = self.win_counter();
+        (0..self.board)
+            .map(|i| {
+                (0..self.board)
+                    .map(|j| count.call(vec![(i, 0), (j, 0)]).ok_or_else(overflow))
+                    .collect()
+            })
+            .collect()
+    }
 
-        for turn in 0.. {
-            match scores.get(&turn) {
-                Some(m) if !m.is_empty() => {}
-                _ => {
-                    break;
-                }
-            }
-            let mut m = map![];
-            for (&(pos1, pos2, sc1, sc2), &cnt) in scores.get(&turn).unwrap() {
-                if sc1 == TARGET || sc2 == TARGET {
-                    *final_scores.entry((sc1, sc2)).or_insert(0) += cnt;
-                    continue;
-                }
-                for (d1, freq1) in POSSIBILITIES {
-                    let new_pos1 = (pos1 + d1) % 10;
-                    let new_sc1 = (sc1 + new_pos1 + 1).min(TARGET);
-                    if new_sc1 == TARGET {
-                        *m.entry((new_pos1, pos2, new_sc1, sc2)).or_insert(0) +=
-                            cnt * freq1;
+    /// Follows all the universes of the game with the Dirac die turn by turn,
+    /// and counts the wins on every turn.
+    fn distribution(&self, positions: &[usize]) -> Result<Distribution, aoc::Error> {
+        let mut games = HashMap::from([(self.start(positions)?, 1_u64)]);
+        let outcomes = self.outcomes();
+        let mut wins = vec![];
+
+        while !games.is_empty() {
+            let current = wins.len() % self.players;
+            let mut won = vec![0_u64; self.players];
+            let mut next = DefaultMap::new(0_u64);
+            for (players, n) in games {
+                for &(steps, freq) in &outcomes {
+                    let mut players = players.clone();
+                    let universes = n.checked_mul(freq).ok_or_else(overflow)?;
+                    let cnt = if self.advance(&mut players[current], steps) {
+                        &mut won[current]
                     } else {
-                        for (d2, freq2) in POSSIBILITIES {
-                            let new_pos2 = (pos2 + d2) % 10;
-                            let new_sc2 = (sc2 + new_pos2 + 1).min(TARGET);
-                            *m.entry((new_pos1, new_pos2, new_sc1, new_sc2))
-                                .or_insert(0) += cnt * (freq1 * freq2);
-                        }
-                    }
+                        &mut next[&players]
+                    };
+                    *cnt = cnt.checked_add(universes).ok_or_else(overflow)?;
                 }
             }
-            scores.insert(turn + 1, m);
+            wins.push(won);
+            games = next.into_inner();
         }
 
-        let wins1: usize = final_scores
-            .iter()
-            .filter(|(&(sc1, _), _)| sc1 == TARGET)
-            .map(|el| el.1)
-            .copied()
-            .sum();
-        let wins2: usize = final_scores
+        Ok(Distribution {
+            branching: outcomes.iter().map(|o| o.1).sum(),
+            wins,
+        })
+    }
+}
+
+fn overflow() -> aoc::Error {
+    aoc::Error::new("Integer overflow in win counts")
+}
+
+/// Universes won by each of the players, turn by turn.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Distribution {
+    /// Number of universes each universe splits into on every turn.
+    branching: u64,
+    /// Universes won by each of the players on every turn, starting from the first.
+    wins: Vec<Vec<u64>>,
+}
+
+impl Distribution {
+    /// Returns the number of universes that each of the players has won
+    /// by the end of the given (1-based) turn.
+    fn won_by(&self, turn: usize) -> Vec<u64> {
+        let players = self.wins.first().map_or(0, Vec::len);
+        self.wins
             .iter()
-            .filter(|(&(sc1, sc2), _)| sc1 < TARGET && sc2 == TARGET)
-            .map(|el| el.1)
-            .copied()
-            .sum();
+            .take(turn)
+            .fold(vec![0; players], |acc, won| {
+                acc.iter().zip(won).map(|(a, w)| a + w).collect()
+            })
+    }
+
+    /// Returns the total number of universes won by each of the players.
+    #[cfg(test)]
+    fn total(&self) -> Vec<u64> {
+        self.won_by(self.wins.len())
+    }
+
+    /// Returns the expected number of turns in the game.
+    fn expected_length(&self) -> f64 {
+        (1..)
+            .zip(&self.wins)
+            .map(|(turn, won)| {
+                let ended = won.iter().sum::<u64>() as f64;
+                turn as f64 * ended / (self.branching as f64).powi(turn)
+            })
+            .sum()
+    }
+}
 
-        wins1.max(wins2)
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for turn in 1..=self.wins.len() {
+            writeln!(
+                f,
+                "turn {:>2}: {}",
+                turn,
+                self.won_by(turn).iter().join(" ")
+            )?;
+        }
+        write!(f, "expected length: {:.3} turns", self.expected_length())
+    }
+}
+
+struct Solver;
+
+impl Solver {
+    fn parse_input(&self, input: Input) -> Vec<usize> {
+        input
+            .map(|line| line.split_once(": ").expect("Expect one delimiter").1)
+            .map(|n| n.parse().unwrap())
+            .collect()
+    }
+}
+
+impl Solution for Solver {
+    type Output = u64;
+
+    fn solve_a(&self, input: Input) -> u64 {
+        let positions = self.parse_input(input);
+        let game = PRACTICE
+            .play(&positions, DeterministicDie::new(PRACTICE.faces))
+            .unwrap();
+        let loser = game.scores.iter().min().unwrap();
+        (game.rolls * loser) as u64
+    }
+
+    fn solve_b(&self, input: Input) -> u64 {
+        let positions = self.parse_input(input);
+        let wins = DIRAC.count_wins(&positions).unwrap();
+        wins.into_iter().max().unwrap()
+    }
+
+    fn explain(&self, part: Part, input: Input) -> Option<String> {
+        if part == Part::A {
+            return None;
+        }
+        let positions = self.parse_input(input);
+        let dist = DIRAC.distribution(&positions).unwrap();
+        let table = DIRAC.win_table().unwrap();
+        let tables = (0..DIRAC.players).map(|k| {
+            let rows = table
+                .iter()
+                .map(|row| row.iter().map(|wins| format!("{:>15}", wins[k])).join(" "))
+                .join("\n");
+            format!(
+                "universes won by player {}, by the starting positions:\n{}",
+                k + 1,
+                rows
+            )
+        });
+        Some([dist.to_string()].into_iter().chain(tables).join("\n\n"))
     }
 }
 
@@ -141,10 +371,202 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 739_785);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 444_356_092_776_315);
+
+        let wins = DIRAC.count_wins(&[4, 8]).unwrap();
+        assert_eq!(wins, vec![444_356_092_776_315, 341_960_390_180_808]);
+
+        assert!(solver.explain(Part::A, Input::new(&input)).is_none());
+        let text = solver.explain(Part::B, Input::new(&input)).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 20 + 2 * (2 + 10));
+        assert_eq!(lines[19], "expected length: 6.919 turns");
+        // players starting at 4 and 8
+        assert_eq!(lines[25].split_whitespace().nth(7), Some("444356092776315"));
+        assert_eq!(lines[37].split_whitespace().nth(7), Some("341960390180808"));
+    }
+
+    #[test]
+    fn test_outcomes() {
+        assert_eq!(
+            DIRAC.outcomes(),
+            vec![(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)]
+        );
+
+        let rules = Rules {
+            faces: 6,
+            rolls: 2,
+            ..DIRAC
+        };
+        let outcomes = rules.outcomes();
+        assert_eq!(outcomes.len(), 11);
+        assert_eq!(outcomes[5], (7, 6));
+        assert_eq!(outcomes.iter().map(|o| o.1).sum::<u64>(), 36);
+    }
+
+    /// Counts the wins by following every universe one by one.
+    fn brute_force(rules: &Rules, players: &mut Vec<Player>, turn: usize) -> Vec<u64> {
+        let mut wins = vec![0; rules.players];
+        let current = turn % rules.players;
+        for rolls in (0..rules.rolls)
+            .map(|_| 1..=rules.faces)
+            .multi_cartesian_product()
+        {
+            let saved = players[current];
+            if rules.advance(&mut players[current], rolls.iter().sum()) {
+                wins[current] += 1;
+            } else {
+                let more = brute_force(rules, players, turn + 1);
+                wins.iter_mut().zip(more).for_each(|(w, m)| *w += m);
+            }
+            players[current] = saved;
+        }
+        wins
+    }
+
+    #[test]
+    fn test_other_rules() {
+        let rules = Rules {
+            players: 3,
+            board: 5,
+            faces: 2,
+            rolls: 2,
+            target: 8,
+        };
+        let positions = [1, 3, 5];
+        let mut players = rules.start(&positions).unwrap();
+        assert_eq!(
+            rules.count_wins(&positions).unwrap(),
+            brute_force(&rules, &mut players, 0)
+        );
+
+        // a die with one face leaves no choice, so there's only one universe
+        let rules = Rules {
+            players: 4,
+            board: 7,
+            faces: 1,
+            rolls: 2,
+            target: 30,
+        };
+        let positions = [2, 7, 1, 4];
+        let game = rules.play(&positions, DeterministicDie::new(1)).unwrap();
+        let wins = rules.count_wins(&positions).unwrap();
+        assert_eq!(wins.iter().sum::<u64>(), 1);
+        assert_eq!(wins[game.winner], 1);
+        assert!(game.scores[game.winner] >= 30);
+        assert_eq!(game.rolls % 2, 0);
+    }
+
+    #[test]
+    fn test_distribution() {
+        let dist = DIRAC.distribution(&[4, 8]).unwrap();
+        assert_eq!(dist.total(), vec![444_356_092_776_315, 341_960_390_180_808]);
+        for positions in [[1, 1], [3, 7], [10, 2]] {
+            assert_eq!(
+                DIRAC.distribution(&positions).unwrap().total(),
+                DIRAC.count_wins(&positions).unwrap()
+            );
+        }
+        assert_eq!(dist.won_by(0), vec![0, 0]);
+        assert_eq!(dist.won_by(4), vec![0, 0]);
+        assert_eq!(dist.won_by(6), vec![3_359_232, 26_079_750]);
+
+        let text = dist.to_string();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 19 + 1);
+        assert_eq!(lines[6], "turn  7: 4483386758 26079750");
+        assert_eq!(lines[19], "expected length: 6.919 turns");
+
+        // every game ends sooner or later
+        let ended: f64 = (1..)
+            .zip(&dist.wins)
+            .map(|(turn, won)| won.iter().sum::<u64>() as f64 / 27_f64.powi(turn))
+            .sum();
+        assert!((ended - 1.0).abs() < 1e-9);
+
+        // a die with one face leaves no choice, so the game always takes as long
+        let rules = Rules {
+            faces: 1,
+            rolls: 2,
+            ..DIRAC
+        };
+        let game = rules.play(&[3, 9], DeterministicDie::new(1)).unwrap();
+        let dist = rules.distribution(&[3, 9]).unwrap();
+        assert_eq!(dist.expected_length(), (game.rolls / 2) as f64);
+    }
+
+    #[test]
+    fn test_win_table() {
+        let table = DIRAC.win_table().unwrap();
+        assert_eq!(table.len(), 10);
+        assert!(table.iter().all(|row| row.len() == 10));
+        assert_eq!(table[3][7], vec![444_356_092_776_315, 341_960_390_180_808]);
+        for (i, j) in [(0, 0), (2, 5), (9, 4)] {
+            assert_eq!(table[i][j], DIRAC.count_wins(&[i + 1, j + 1]).unwrap());
+        }
+
+        let rules = Rules {
+            players: 3,
+            ..DIRAC
+        };
+        assert_eq!(
+            rules.win_table().unwrap_err().to_string(),
+            "Error: Win table needs 2 players, found 3"
+        );
+    }
+
+    #[test]
+    fn test_invalid_games() {
+        for (rules, positions, err) in [
+            (DIRAC, vec![4], "Expected 2 players, found 1"),
+            (DIRAC, vec![4, 11], "Player 2 starts off the board at 11"),
+            (DIRAC, vec![0, 1], "Player 1 starts off the board at 0"),
+            (
+                Rules { rolls: 0, ..DIRAC },
+                vec![1, 2],
+                "Game can't be played by Rules { players: 2, board: 10, \
+                faces: 3, rolls: 0, target: 21 }",
+            ),
+            (
+                Rules {
+                    players: 0,
+                    ..DIRAC
+                },
+                vec![],
+                "Game can't be played by Rules { players: 0, board: 10, \
+                faces: 3, rolls: 3, target: 21 }",
+            ),
+            (
+                Rules {
+                    faces: 100,
+                    rolls: 10,
+                    ..DIRAC
+                },
+                vec![1, 2],
+                "Universes split into too many on every turn",
+            ),
+        ] {
+            let actual = rules.count_wins(&positions).unwrap_err();
+            assert_eq!(actual.to_string(), format!("Error: {}", err));
+        }
+        assert!(Rules {
+            players: 0,
+            ..PRACTICE
+        }
+        .play(&[], DeterministicDie::new(100))
+        .is_err());
+
+        // the counts outgrow u64 by the time anyone gets 30 points
+        let rules = Rules {
+            target: 30,
+            ..DIRAC
+        };
+        let err = rules.count_wins(&[4, 8]).unwrap_err();
+        assert_eq!(err.to_string(), "Error: Integer overflow in win counts");
+        assert!(rules.distribution(&[4, 8]).is_err());
     }
 }
@@ -1,8 +1,7 @@
-use adventofcode::{self as aoc, aoc_problem, map, Solution};
+use adventofcode::{self as aoc, aoc_problem, map, Input, Solution};
 use itertools::Itertools;
 use std::collections::HashMap;
 use std::ops::Range;
-use std::str::Lines;
 
 struct Solver;
 
@@ -20,7 +19,7 @@ fn parse_intervals(line: &str) -> [Range<i32>; 3] {
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, input: Lines) -> usize {
+    fn solve_a(&self, input: Input) -> usize {
         let inp: Vec<_> = input
             .map(|line| line.split_once(' ').expect("Expect one delimiter"))
             .map(|(op, line)| (op, parse_intervals(line)))
@@ -41,7 +40,7 @@ impl Solution for Solver {
         cubes.values().filter(|&&v| v == 1).count()
     }
 
-    fn solve_b(&self, input: Lines) -> usize {
+    fn solve_b(&self, input: Input) -> usize {
         let inp: Vec<_> = input
             .map(|line| line.split_once(' ').expect("Expect one delimiter"))
             .map(|(op, line)| (op, parse_intervals(line)))
@@ -135,7 +134,7 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 590_784);
     }
 
@@ -207,7 +206,7 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 2_758_514_936_282_235);
     }
 }
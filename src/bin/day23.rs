@@ -1,205 +1,497 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
-use std::collections::{BinaryHeap, VecDeque};
-use std::str::Lines;
+use adventofcode::{
+    self as aoc, aoc_problem, make_err, util::problem::Part, Input, Solution,
+};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+
+/// Contents of every cell in the burrow: the hallway cells from left to right,
+/// then the cells of every room from top to bottom. Empty cells are 0,
+/// and amphipods of the kind `k` (`A` is 0) are `k + 1`.
+type State = Vec<u8>;
+
+fn apod_name(kind: u8) -> char {
+    (b'A' + kind) as char
+}
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum Apod {
-    A,
-    B,
-    C,
-    D,
+fn apod_energy(kind: u8) -> usize {
+    10_usize.pow(kind as u32)
 }
 
-macro_rules! impl_apod_attr {
-    ($meth:ident() -> $rt:ty, [$a:expr, $b:expr, $c:expr, $d:expr]) => {
-        impl Apod {
-            fn $meth(&self) -> $rt {
-                match self {
-                    Apod::A => $a,
-                    Apod::B => $b,
-                    Apod::C => $c,
-                    Apod::D => $d,
-                }
-            }
-        }
-    };
+/// Location in the burrow.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Cell {
+    /// Hallway cell, counted from the left.
+    Hall(usize),
+    /// Room, counted from the left, and the depth in it (0 is the top).
+    Room(usize, usize),
 }
 
-impl_apod_attr!(dest() -> usize, [2, 4, 6, 8]);
-impl_apod_attr!(coeff() -> usize, [1, 10, 100, 1000]);
-
-impl From<&str> for Apod {
-    fn from(s: &str) -> Self {
-        use Apod::*;
-        match s {
-            "A" => A,
-            "B" => B,
-            "C" => C,
-            "D" => D,
-            _ => panic!(),
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cell::Hall(h) => write!(f, "hallway cell {}", h + 1),
+            Cell::Room(r, d) => write!(f, "room {} cell {}", r + 1, d + 1),
         }
     }
 }
 
-struct Solver;
+/// Amphipod moving from one cell to another, and the energy it spends.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Move {
+    apod: char,
+    from: Cell,
+    to: Cell,
+    energy: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} moves from {} to {} using {} energy",
+            self.apod, self.from, self.to, self.energy
+        )
+    }
+}
 
-fn check(rooms: &[VecDeque<Apod>; 4], cap: usize) -> bool {
-    rooms
-        .iter()
-        .zip([Apod::A, Apod::B, Apod::C, Apod::D])
-        .all(|(r, apod)| r.len() == cap && r.iter().all(|&a| a == apod))
+/// Layout of the burrow: a straight hallway, and rooms of equal depth below it.
+/// The amphipods of the kind `k` belong to the room `k`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Burrow {
+    hall_len: usize,
+    /// Hallway cells just outside the rooms, where amphipods never stop.
+    entrances: Vec<usize>,
+    depth: usize,
 }
 
-fn dfs(
-    rooms: &mut [VecDeque<Apod>; 4],
-    hall: &mut [Option<Apod>; 11],
-    best: &mut BinaryHeap<usize>,
-    score: usize,
-    cap: usize,
-) -> usize {
-    if check(rooms, cap) {
-        match best.peek() {
-            Some(sc) if *sc <= score => {}
-            _ => {
-                // println!("New min energy: {}", score);
-                best.push(score);
-                while best.len() > 1 {
-                    best.pop();
+impl Burrow {
+    /// Parses the diagram of the burrow, and returns its layout along with
+    /// the initial state.
+    fn parse(input: Input) -> Result<(Self, State), aoc::Error> {
+        let is_cell = |c: char| c == '.' || c.is_ascii_uppercase();
+        let cells = |line: &str| {
+            line.chars()
+                .enumerate()
+                .filter(|&(_, c)| is_cell(c))
+                .collect::<Vec<_>>()
+        };
+
+        let mut lines = input.numbered_lines().skip(1);
+        let hall = match lines.next() {
+            Some((_, line)) => cells(line),
+            None => return make_err!("Expected the hallway in line 2"),
+        };
+        if hall.is_empty() || hall.last().unwrap().0 - hall[0].0 + 1 != hall.len() {
+            return make_err!("line 2: hallway should be a straight line of cells");
+        }
+        let hall_start = hall[0].0;
+
+        let mut rows: Vec<Vec<(usize, char)>> = vec![];
+        for (no, line) in lines {
+            let row = cells(line);
+            if row.is_empty() {
+                break;
+            }
+            if let Some(first) = rows.first() {
+                if first.iter().map(|c| c.0).ne(row.iter().map(|c| c.0)) {
+                    return make_err!(
+                        "line {}: rooms don't line up with the ones above",
+                        no
+                    );
+                }
+            }
+            if let Some(&(col, _)) = row.iter().find(|&&(col, _)| {
+                col <= hall_start || col >= hall_start + hall.len() - 1
+            }) {
+                return make_err!(
+                    "line {}: room at column {} can't be entered",
+                    no,
+                    col + 1
+                );
+            }
+            rows.push(row);
+        }
+        if rows.is_empty() {
+            return make_err!("Expected rooms below the hallway");
+        }
+
+        let burrow = Burrow {
+            hall_len: hall.len(),
+            entrances: rows[0].iter().map(|&(col, _)| col - hall_start).collect(),
+            depth: rows.len(),
+        };
+
+        let mut state =
+            vec![0; burrow.hall_len + burrow.entrances.len() * burrow.depth];
+        let hall_cells = hall.iter().map(|&(i, c)| (Cell::Hall(i - hall_start), c));
+        let room_cells = rows.iter().enumerate().flat_map(|(d, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(r, &(_, c))| (Cell::Room(r, d), c))
+        });
+        for (cell, c) in hall_cells.chain(room_cells) {
+            if c == '.' {
+                continue;
+            }
+            let kind = c as u8 - b'A';
+            if kind as usize >= burrow.entrances.len() {
+                return make_err!("Amphipod {} has no room", c);
+            }
+            if let Cell::Hall(h) = cell {
+                if burrow.entrances.contains(&h) {
+                    return make_err!("Amphipod {} blocks the entrance to a room", c);
                 }
             }
+            state[burrow.index(cell)] = kind + 1;
+        }
+
+        for kind in 0..burrow.entrances.len() as u8 {
+            let count = state.iter().filter(|&&k| k == kind + 1).count();
+            if count != burrow.depth {
+                return make_err!(
+                    "Expected {} amphipods {}, found {}",
+                    burrow.depth,
+                    apod_name(kind),
+                    count
+                );
+            }
         }
-        return score;
+
+        Ok((burrow, state))
     }
 
-    // move from hall into room
-    for idx in [0, 1, 3, 5, 7, 9, 10] {
-        let mut tot = usize::MAX;
-        if hall[idx].is_none() {
-            continue;
+    /// Returns the diagram of the burrow in the given state.
+    fn show<'a>(&'a self, state: &'a [u8]) -> Diagram<'a> {
+        Diagram(self, state)
+    }
+
+    /// Replays the moves from the given state, one step at a time.
+    fn replay<'a>(&'a self, start: &[u8], moves: &'a [Move]) -> Replay<'a> {
+        Replay {
+            burrow: self,
+            state: start.to_vec(),
+            moves: moves.iter(),
+            total: 0,
         }
-        let apod = hall[idx].unwrap();
-        let dst = apod.dest();
-        let coeff = apod.coeff();
-
-        let ri = dst / 2 - 1;
-        hall[idx] = None;
-
-        if rooms[ri].iter().all(|&a| a == apod)
-            && rooms[ri].len() < cap
-            && hall[dst.min(idx)..=dst.max(idx)]
-                .iter()
-                .all(|a| a.is_none())
-        {
-            rooms[ri].push_front(apod);
-            tot = tot.min(dfs(
-                rooms,
-                hall,
-                best,
-                score
-                    + coeff * (dst.max(idx) - dst.min(idx) + 1 + cap - rooms[ri].len()),
-                cap,
-            ));
-            rooms[ri].pop_front();
+    }
+
+    fn index(&self, cell: Cell) -> usize {
+        match cell {
+            Cell::Hall(h) => h,
+            Cell::Room(r, d) => self.hall_len + r * self.depth + d,
         }
-        hall[idx] = Some(apod);
-        if tot != usize::MAX {
-            return tot;
+    }
+
+    fn room<'a>(&self, state: &'a [u8], r: usize) -> &'a [u8] {
+        let start = self.index(Cell::Room(r, 0));
+        &state[start..start + self.depth]
+    }
+
+    /// Checks that the hallway is free between the cells `from` and `to`,
+    /// excluding `from` itself.
+    fn hall_clear(&self, state: &[u8], from: usize, to: usize) -> bool {
+        let (lo, hi) = if from < to {
+            (from + 1, to)
+        } else {
+            (to, from - 1)
+        };
+        lo > hi || state[lo..=hi].iter().all(|&k| k == 0)
+    }
+
+    /// Returns the depth that an amphipod of the kind `kind` would take in its room,
+    /// if the room holds no strangers.
+    fn free_depth(&self, state: &[u8], kind: u8) -> Option<usize> {
+        let room = self.room(state, kind as usize);
+        let empty = room.iter().take_while(|&&k| k == 0).count();
+        match empty {
+            0 => None,
+            _ if room[empty..].iter().all(|&k| k == kind + 1) => Some(empty - 1),
+            _ => None,
         }
     }
 
-    // move from room into hall
-    let mut totals = vec![];
-    for i in 0..4 {
-        let src = 2 * (i + 1);
-        if rooms[i].is_empty() {
-            continue;
+    fn is_organized(&self, state: &[u8]) -> bool {
+        (0..self.entrances.len())
+            .all(|r| self.room(state, r).iter().all(|&k| k as usize == r + 1))
+    }
+
+    fn make_move(
+        &self,
+        state: &[u8],
+        from: Cell,
+        to: Cell,
+        steps: usize,
+    ) -> (State, Move) {
+        let mut next = state.to_vec();
+        let kind = next[self.index(from)] - 1;
+        next.swap(self.index(from), self.index(to));
+        let mv = Move {
+            apod: apod_name(kind),
+            from,
+            to,
+            energy: steps * apod_energy(kind),
+        };
+        (next, mv)
+    }
+
+    /// Lists the moves from the given state. If some amphipod can reach its room,
+    /// that's the only move, since it's never worse to make it right away.
+    fn moves(&self, state: &[u8]) -> Vec<(State, Move)> {
+        let mut moves = vec![];
+
+        // from the hallway into the room
+        for h in 0..self.hall_len {
+            let kind = match state[h] {
+                0 => continue,
+                k => k - 1,
+            };
+            let entrance = self.entrances[kind as usize];
+            if let Some(d) = self.free_depth(state, kind) {
+                if self.hall_clear(state, h, entrance) {
+                    let steps = h.max(entrance) - h.min(entrance) + d + 1;
+                    return vec![self.make_move(
+                        state,
+                        Cell::Hall(h),
+                        Cell::Room(kind as usize, d),
+                        steps,
+                    )];
+                }
+            }
         }
-        let apod = rooms[i].pop_front().unwrap();
-        let dst = apod.dest();
-        let coeff = apod.coeff();
-
-        for idx in [0, 1, 3, 5, 7, 9, 10] {
-            if (src != dst || !rooms[i].iter().all(|&a| a == apod))
-                && hall[src.min(idx)..=src.max(idx)]
-                    .iter()
-                    .all(|a| a.is_none())
-            {
-                hall[idx] = Some(apod);
-                let tot = dfs(
-                    rooms,
-                    hall,
-                    best,
-                    score
-                        + coeff * (src.max(idx) - src.min(idx) + cap - rooms[i].len()),
-                    cap,
-                );
-                totals.push(tot);
-                hall[idx] = None;
+
+        // out of the room, into the hallway or right into the other room
+        for (r, &entrance) in self.entrances.iter().enumerate() {
+            let room = self.room(state, r);
+            let top = match room.iter().position(|&k| k != 0) {
+                Some(top) => top,
+                None => continue,
+            };
+            if room[top..].iter().all(|&k| k as usize == r + 1) {
+                continue;
+            }
+            let kind = room[top] - 1;
+            let from = Cell::Room(r, top);
+
+            let target = self.entrances[kind as usize];
+            if let Some(d) = self.free_depth(state, kind) {
+                if self.hall_clear(state, entrance, target) {
+                    let steps =
+                        top + 1 + target.max(entrance) - target.min(entrance) + d + 1;
+                    return vec![self.make_move(
+                        state,
+                        from,
+                        Cell::Room(kind as usize, d),
+                        steps,
+                    )];
+                }
+            }
+
+            for h in 0..self.hall_len {
+                if !self.entrances.contains(&h)
+                    && state[h] == 0
+                    && self.hall_clear(state, entrance, h)
+                {
+                    let steps = top + 1 + h.max(entrance) - h.min(entrance);
+                    moves.push(self.make_move(state, from, Cell::Hall(h), steps));
+                }
             }
         }
 
-        rooms[i].push_front(apod);
+        moves
+    }
+
+    /// Lower bound of the energy needed to organize the amphipods.
+    fn estimate(&self, state: &[u8]) -> usize {
+        let mut energy = 0;
+        for (h, &k) in state[..self.hall_len].iter().enumerate() {
+            if k > 0 {
+                let kind = k - 1;
+                let target = self.entrances[kind as usize];
+                energy += (h.max(target) - h.min(target) + 1) * apod_energy(kind);
+            }
+        }
+        for (r, &entrance) in self.entrances.iter().enumerate() {
+            let room = self.room(state, r);
+            for (d, &k) in room.iter().enumerate() {
+                if k == 0 || room[d..].iter().all(|&k| k as usize == r + 1) {
+                    continue;
+                }
+                let kind = k - 1;
+                let target = self.entrances[kind as usize];
+                // a stranger walks to its room, while a local steps aside and back
+                let across = match target.max(entrance) - target.min(entrance) {
+                    0 => 2,
+                    dist => dist,
+                };
+                energy += (d + 1 + across + 1) * apod_energy(kind);
+            }
+        }
+        energy
     }
 
-    totals.into_iter().min().unwrap_or(usize::MAX)
+    /// Finds the moves that organize the amphipods with the least energy,
+    /// using A* search over the states of the burrow.
+    fn organize(&self, start: &[u8]) -> Result<Vec<Move>, aoc::Error> {
+        let mut best: HashMap<State, usize> = HashMap::new();
+        let mut prev: HashMap<State, (State, Move)> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        best.insert(start.to_vec(), 0);
+        queue.push(Reverse((self.estimate(start), 0, start.to_vec())));
+
+        while let Some(Reverse((_, energy, state))) = queue.pop() {
+            if energy > best[&state] {
+                continue;
+            }
+            if self.is_organized(&state) {
+                let mut moves = vec![];
+                let mut cur = state;
+                while let Some((before, mv)) = prev.remove(&cur) {
+                    moves.push(mv);
+                    cur = before;
+                }
+                moves.reverse();
+                return Ok(moves);
+            }
+
+            for (next, mv) in self.moves(&state) {
+                let total = energy + mv.energy;
+                if best.get(&next).map_or(true, |&e| total < e) {
+                    best.insert(next.clone(), total);
+                    prev.insert(next.clone(), (state.clone(), mv));
+                    queue.push(Reverse((total + self.estimate(&next), total, next)));
+                }
+            }
+        }
+
+        make_err!("Amphipods can't be organized")
+    }
 }
 
-impl Solution for Solver {
-    type Output = usize;
+/// Diagram of the burrow in some state, drawn like in the puzzle.
+struct Diagram<'a>(&'a Burrow, &'a [u8]);
+
+impl fmt::Display for Diagram<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Diagram(burrow, state) = *self;
+        let cell = |cell| match state[burrow.index(cell)] {
+            0 => '.',
+            k => apod_name(k - 1),
+        };
+        let width = burrow.hall_len + 2;
+
+        writeln!(f, "{}", "#".repeat(width))?;
+        let hall: String = (0..burrow.hall_len).map(|h| cell(Cell::Hall(h))).collect();
+        writeln!(f, "#{}#", hall)?;
+
+        // rooms are drawn in the columns right below their entrances
+        let left = burrow.entrances[0];
+        let right = burrow.entrances[burrow.entrances.len() - 1] + 2;
+        for d in 0..burrow.depth {
+            let line: String = (0..width)
+                .map(
+                    |col| match burrow.entrances.iter().position(|&e| e + 1 == col) {
+                        Some(r) => cell(Cell::Room(r, d)),
+                        None if d > 0 && !(left..=right).contains(&col) => ' ',
+                        None => '#',
+                    },
+                )
+                .collect();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        write!(f, "{}{}", " ".repeat(left), "#".repeat(right - left + 1))
+    }
+}
 
-    fn solve_a(&self, input: Lines) -> usize {
-        let mut rms = [
-            VecDeque::with_capacity(2),
-            VecDeque::with_capacity(2),
-            VecDeque::with_capacity(2),
-            VecDeque::with_capacity(2),
-        ];
+/// Step of the replay: the move, the burrow after it, and the energy spent so far.
+struct Step<'a> {
+    burrow: &'a Burrow,
+    state: State,
+    mv: Move,
+    total: usize,
+}
 
-        input.skip(2).take(2).for_each(|line| {
-            line.split('#')
-                .filter(|el| !el.trim().is_empty())
-                .enumerate()
-                .for_each(|(i, l)| rms[i].push_back(Apod::from(l)))
-        });
+impl fmt::Display for Step<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}, {} in total", self.mv, self.total)?;
+        write!(f, "{}", self.burrow.show(&self.state))
+    }
+}
 
-        let mut hall = [None; 11];
-        let mut best = BinaryHeap::new();
+struct Replay<'a> {
+    burrow: &'a Burrow,
+    state: State,
+    moves: std::slice::Iter<'a, Move>,
+    total: usize,
+}
 
-        dfs(&mut rms, &mut hall, &mut best, 0, 2);
-        best.pop().unwrap()
+impl<'a> Iterator for Replay<'a> {
+    type Item = Step<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &mv = self.moves.next()?;
+        let (from, to) = (self.burrow.index(mv.from), self.burrow.index(mv.to));
+        self.state.swap(from, to);
+        self.total += mv.energy;
+        Some(Step {
+            burrow: self.burrow,
+            state: self.state.clone(),
+            mv,
+            total: self.total,
+        })
     }
+}
 
-    fn solve_b(&self, input: Lines) -> usize {
-        let mut rms = [
-            VecDeque::with_capacity(4),
-            VecDeque::with_capacity(4),
-            VecDeque::with_capacity(4),
-            VecDeque::with_capacity(4),
-        ];
+struct Solver;
 
-        input
-            .skip(2)
-            .take(2)
-            .enumerate()
-            .flat_map(|(i, line)| {
-                if i == 0 {
-                    [line, "  #D#C#B#A#"]
-                } else {
-                    ["  #D#B#A#C#", line]
-                }
-            })
-            .for_each(|line| {
-                line.split('#')
-                    .filter(|el| !el.trim().is_empty())
-                    .enumerate()
-                    .for_each(|(i, l)| rms[i].push_back(Apod::from(l)))
-            });
-
-        let mut hall = [None; 11];
-        let mut best = BinaryHeap::new();
-
-        dfs(&mut rms, &mut hall, &mut best, 0, 4);
-        best.pop().unwrap()
+impl Solver {
+    fn parse_input(&self, input: Input) -> (Burrow, State) {
+        Burrow::parse(input).unwrap()
+    }
+
+    /// Inserts the folded lines of the diagram, for part B.
+    fn unfold(&self, input: Input) -> String {
+        let mut lines: Vec<_> = input.collect();
+        lines.splice(3..3, ["  #D#C#B#A#", "  #D#B#A#C#"]);
+        lines.join("\n")
+    }
+
+    fn solve(&self, input: Input) -> usize {
+        let (burrow, state) = self.parse_input(input);
+        let moves = burrow.organize(&state).unwrap();
+        moves.iter().map(|mv| mv.energy).sum()
+    }
+
+    /// Shows every move of the cheapest way to organize the amphipods.
+    fn replay(&self, input: Input) -> String {
+        let (burrow, state) = self.parse_input(input);
+        let moves = burrow.organize(&state).unwrap();
+        burrow
+            .replay(&state, &moves)
+            .map(|step| step.to_string())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+impl Solution for Solver {
+    type Output = usize;
+
+    fn solve_a(&self, input: Input) -> usize {
+        self.solve(input)
+    }
+
+    fn solve_b(&self, input: Input) -> usize {
+        self.solve(Input::new(&self.unfold(input)))
+    }
+
+    fn explain(&self, part: Part, input: Input) -> Option<String> {
+        let replay = match part {
+            Part::A => self.replay(input),
+            Part::B => self.replay(Input::new(&self.unfold(input))),
+        };
+        Some(replay)
     }
 }
 
@@ -214,7 +506,6 @@ fn main() {
     }
 }
 
-// Need to run these tests in --release mode to speed them up
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,11 +524,15 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 12521);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 44169);
+
+        let replay = solver.explain(Part::B, Input::new(&input)).unwrap();
+        assert_eq!(replay.matches(" in total").count(), 22);
+        assert!(replay.contains("room 4 cell 1 using 3000 energy, 44169 in total\n"));
     }
 
     #[test]
@@ -253,10 +548,144 @@ mod tests {
         .trim()
         .strip_margin();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 18282);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 50132);
     }
+
+    #[test]
+    fn test_moves() {
+        let input = r"
+            |#############
+            |#...........#
+            |###B#C#B#D###
+            |  #A#D#C#A#
+            |  #########
+        "
+        .trim()
+        .strip_margin();
+        let (burrow, state) = Burrow::parse(Input::new(&input)).unwrap();
+        let moves = burrow.organize(&state).unwrap();
+
+        assert_eq!(moves.len(), 10);
+        assert_eq!(
+            moves[1],
+            Move {
+                apod: 'C',
+                from: Cell::Room(1, 0),
+                to: Cell::Room(2, 0),
+                energy: 400
+            }
+        );
+        assert_eq!(
+            moves.iter().map(|mv| mv.energy).collect::<Vec<_>>(),
+            vec![40, 400, 3000, 30, 40, 2000, 3, 3000, 4000, 8]
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let input = r"
+            |#############
+            |#...........#
+            |###B#C#B#D###
+            |  #A#D#C#A#
+            |  #########
+        "
+        .trim()
+        .strip_margin();
+        let (burrow, state) = Burrow::parse(Input::new(&input)).unwrap();
+        assert_eq!(burrow.show(&state).to_string(), input);
+
+        let moves = burrow.organize(&state).unwrap();
+        let steps: Vec<_> = burrow.replay(&state, &moves).collect();
+        assert_eq!(steps.len(), 10);
+
+        let expected = r"
+            |B moves from room 3 cell 1 to hallway cell 4 using 40 energy, 40 in total
+            |#############
+            |#...B.......#
+            |###B#C#.#D###
+            |  #A#D#C#A#
+            |  #########
+        "
+        .trim()
+        .strip_margin();
+        assert_eq!(steps[0].to_string(), expected);
+
+        let expected = r"
+            |A moves from hallway cell 10 to room 1 cell 1 using 8 energy, 12521 in total
+            |#############
+            |#...........#
+            |###A#B#C#D###
+            |  #A#B#C#D#
+            |  #########
+        "
+        .trim()
+        .strip_margin();
+        assert_eq!(steps[9].to_string(), expected);
+
+        // the unfolded diagram of the narrower burrow
+        let input = "#########\n#.B.....#\n###A#.###\n  #A#B#\n  #####";
+        let (burrow, state) = Burrow::parse(Input::new(input)).unwrap();
+        assert_eq!(burrow.show(&state).to_string(), input);
+    }
+
+    #[test]
+    fn test_other_layouts() {
+        let input = r"
+            |###########
+            |#.........#
+            |###B#C#A###
+            |  #A#B#C#
+            |  #######
+        "
+        .trim()
+        .strip_margin();
+        let (burrow, state) = Burrow::parse(Input::new(&input)).unwrap();
+        assert_eq!(burrow.entrances, vec![2, 4, 6]);
+        let moves = burrow.organize(&state).unwrap();
+        assert_eq!(moves.iter().map(|mv| mv.energy).sum::<usize>(), 448);
+
+        // the amphipod in the hallway only has to walk into its room
+        let input = "#########\n#.B.....#\n###A#.###\n  #A#B#\n  #####";
+        let (burrow, state) = Burrow::parse(Input::new(input)).unwrap();
+        let moves = burrow.organize(&state).unwrap();
+        assert_eq!(
+            moves,
+            vec![Move {
+                apod: 'B',
+                from: Cell::Hall(1),
+                to: Cell::Room(1, 0),
+                energy: 40
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_burrows() {
+        let test_data = [
+            (
+                "#######\n#.....#\n###B#A###",
+                "line 3: room at column 6 can't be entered",
+            ),
+            (
+                "#########\n#.......#\n###B#A###\n  #AB#",
+                "line 4: rooms don't line up with the ones above",
+            ),
+            (
+                "#########\n#.......#\n###B#B###",
+                "Expected 1 amphipods A, found 0",
+            ),
+            ("#########\n#.......#\n###B#C###", "Amphipod C has no room"),
+            ("#########\n#.......#", "Expected rooms below the hallway"),
+        ];
+
+        for &(input, msg) in &test_data {
+            let err = Burrow::parse(Input::new(input)).unwrap_err();
+            assert_eq!(err.to_string(), format!("Error: {}", msg));
+        }
+    }
 }
@@ -1,226 +1,597 @@
-use adventofcode::{self as aoc, aoc_problem, Solution};
-use std::collections::HashSet;
-use std::str::Lines;
-// use std::fmt;
-// use std::rc::Rc;
-// use itertools::Itertools;
-//
-// #[derive(Clone, Debug)]
-// enum Op {
-//     Add,
-//     Mul,
-//     Div,
-//     Rem,
-//     Eql,
-// }
-//
-// impl fmt::Display for Op {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         use Op::*;
-//         match self {
-//             Add => write!(f, "+"),
-//             Mul => write!(f, "*"),
-//             Div => write!(f, "/"),
-//             Rem => write!(f, "%"),
-//             Eql => write!(f, "="),
-//         }
-//     }
-// }
-//
-// #[derive(Clone, Debug)]
-// enum Cons {
-//     Lit(i64),
-//     Var(String),
-//     Expr(Op, Rc<Cons>, Rc<Cons>),
-// }
-//
-// impl Cons {
-//     fn new_expr(op: Op, left: Cons, right: Cons) -> Cons {
-//         use Cons::*;
-//         use Op::*;
-//         match (op, left, right) {
-//             (op, Lit(l), Lit(r)) => match op {
-//                 Add => Lit(l + r),
-//                 Mul => Lit(l * r),
-//                 Div => Lit(l / r),
-//                 Rem => Lit(l % r),
-//                 Eql => Lit(if l == r { 1 } else { 0 }),
-//             },
-//             // eql (vars are always 1 <= v <= 9)
-//             (Eql, Lit(v), Var(_)) if !(1..=9).contains(&v) => Lit(0),
-//             (Eql, Var(_), Lit(v)) if !(1..=9).contains(&v) => Lit(0),
-//             // mul by 0
-//             (Mul, Lit(0), _) => Lit(0),
-//             (Mul, _, Lit(0)) => Lit(0),
-//             // mul by 1
-//             (Mul, l, Lit(1)) => l,
-//             (Mul, Lit(1), r) => r,
-//             // add 0
-//             (Add, l, Lit(0)) => l,
-//             (Add, Lit(0), r) => r,
-//             // else
-//             (op, l, r) => Expr(op, Rc::new(l), Rc::new(r)),
-//         }
-//     }
-// }
-//
-// impl fmt::Display for Cons {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         match self {
-//             Cons::Lit(a) => write!(f, "{}", a),
-//             Cons::Var(w) => write!(f, "{}", w),
-//             Cons::Expr(op, left, right) => {
-//                 write!(f, "({} {} {})", op, left, right)
-//             }
-//         }
-//     }
-// }
-//
-// struct Alu<'a> {
-//     instructions: &'a [&'a str],
-//     registers: HashMap<&'static str, Cons>,
-// }
-//
-// impl fmt::Display for Alu<'_> {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         write!(
-//             f,
-//             "ALU: w={}  x={}  y={}  z={}",
-//             self.registers["w"],
-//             self.registers["x"],
-//             self.registers["y"],
-//             self.registers["z"]
-//         )
-//     }
-// }
-//
-// impl<'a> Alu<'a> {
-//     fn new(instructions: &'a [&'a str]) -> Self {
-//         Self {
-//             instructions,
-//             registers: map![
-//                 "w" => Cons::Lit(0),
-//                 "x" => Cons::Lit(0),
-//                 "y" => Cons::Lit(0),
-//                 "z" => Cons::Lit(0)],
-//         }
-//     }
-//     fn eval(&mut self, inputs: VecDeque<Cons>) -> Cons {
-//         let mut inps = inputs;
-//
-//         for line in self.instructions {
-//             let (op, rest) = line.split_once(' ').unwrap();
-//             match op {
-//                 "inp" => match inps.pop_front() {
-//                     None => {
-//                         return self.registers["z"].clone();
-//                     }
-//                     Some(v) => {
-//                         let reg = match rest {
-//                             "w" => "w",
-//                             "x" => "x",
-//                             "y" => "y",
-//                             "z" => "z",
-//                             _ => unreachable!()
-//                         };
-//                         self.registers.insert(reg, v);
-//                     }
-//                 },
-//                 op => {
-//                     let (reg, val) = rest.split_once(' ').unwrap();
-//                     let val = self
-//                         .registers
-//                         .get(val)
-//                         .cloned()
-//                         .unwrap_or_else(|| Cons::Lit(val.parse().unwrap()));
-//                     let reg = self.registers.get_mut(reg).unwrap();
-//                     let op = match op {
-//                         "add" => Op::Add,
-//                         "mul" => Op::Mul,
-//                         "div" => Op::Div,
-//                         "mod" => Op::Rem,
-//                         "eql" => Op::Eql,
-//                         _ => panic!(),
-//                     };
-//                     *reg = Cons::new_expr(op, reg.clone(), val);
-//                 }
-//             }
-//         }
-//
-//         self.registers["z"].clone()
-//     }
-// }
-
-fn get_model<T>(search_range: T) -> usize
-where
-    T: Clone + Iterator<Item = i8>,
-{
-    // d1 + 4 = dE
-    // d2 + 6 = dD
-    // d3 - 6 = dC
-    // d4 - 1 = d9
-    // d5 + 0 = d6
-    // d7 - 4 = d8
-    // dA + 7 = dB
-
-    let mut digits = [0; 15];
-    let allowed: HashSet<_> = search_range.clone().collect();
-
-    for ch in search_range {
-        for pos in 1..=14 {
-            if digits[pos] != 0 {
-                continue;
+use adventofcode::{self as aoc, aoc_problem, make_err, Input, Solution};
+use itertools::Itertools;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Op {
+    Add,
+    Mul,
+    Div,
+    Rem,
+    Eql,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Op::*;
+        match self {
+            Add => write!(f, "+"),
+            Mul => write!(f, "*"),
+            Div => write!(f, "/"),
+            Rem => write!(f, "%"),
+            Eql => write!(f, "="),
+        }
+    }
+}
+
+impl Op {
+    fn mnemonic(&self) -> &'static str {
+        match self {
+            Op::Add => "add",
+            Op::Mul => "mul",
+            Op::Div => "div",
+            Op::Rem => "mod",
+            Op::Eql => "eql",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Reg {
+    W,
+    X,
+    Y,
+    Z,
+}
+
+impl FromStr for Reg {
+    type Err = aoc::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "w" => Ok(Reg::W),
+            "x" => Ok(Reg::X),
+            "y" => Ok(Reg::Y),
+            "z" => Ok(Reg::Z),
+            _ => make_err!("Unknown register: {}", s),
+        }
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Reg::W => "w",
+            Reg::X => "x",
+            Reg::Y => "y",
+            Reg::Z => "z",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Operand {
+    Reg(Reg),
+    Lit(i64),
+}
+
+impl FromStr for Operand {
+    type Err = aoc::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match (s.parse(), s.parse()) {
+            (Ok(reg), _) => Ok(Operand::Reg(reg)),
+            (_, Ok(val)) => Ok(Operand::Lit(val)),
+            _ => make_err!("Expected register or number: {}", s),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Reg(reg) => write!(f, "{}", reg),
+            Operand::Lit(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Instr {
+    Inp(Reg),
+    Bin(Op, Reg, Operand),
+}
+
+impl FromStr for Instr {
+    type Err = aoc::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<_> = s.split_whitespace().collect();
+        let op = match tokens[..] {
+            ["inp", reg] => return Ok(Instr::Inp(reg.parse()?)),
+            [op, _, _] => match op {
+                "add" => Op::Add,
+                "mul" => Op::Mul,
+                "div" => Op::Div,
+                "mod" => Op::Rem,
+                "eql" => Op::Eql,
+                _ => return make_err!("Unknown instruction: {}", op),
+            },
+            _ => return make_err!("Malformed instruction: {}", s),
+        };
+        Ok(Instr::Bin(op, tokens[1].parse()?, tokens[2].parse()?))
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::Inp(reg) => write!(f, "inp {}", reg),
+            Instr::Bin(op, reg, val) => write!(f, "{} {} {}", op.mnemonic(), reg, val),
+        }
+    }
+}
+
+/// Applies the operation to two numbers, or returns `Err` if the result is undefined.
+fn apply(op: Op, a: i64, b: i64) -> Result<i64, aoc::Error> {
+    let res = match op {
+        Op::Add => a.checked_add(b),
+        Op::Mul => a.checked_mul(b),
+        Op::Div if b == 0 => return make_err!("division by zero"),
+        Op::Div => a.checked_div(b),
+        Op::Rem if a < 0 || b <= 0 => {
+            return make_err!("modulo of {} by {} is undefined", a, b);
+        }
+        Op::Rem => a.checked_rem(b),
+        Op::Eql => Some((a == b) as i64),
+    };
+    match res {
+        Some(res) => Ok(res),
+        None => make_err!("overflow in {} {} {}", a, op, b),
+    }
+}
+
+/// Inclusive bounds of the values that an expression can take.
+type Range = (i64, i64);
+
+/// Symbolic value of a register, as an expression over the input digits.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Cons {
+    Lit(i64),
+    /// Digit read by the n-th `inp` instruction (1-based), always in `1..=9`.
+    Var(usize),
+    Expr(Rc<Expr>),
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Expr {
+    op: Op,
+    left: Cons,
+    right: Cons,
+    range: Range,
+}
+
+impl Cons {
+    fn range(&self) -> Range {
+        match self {
+            Cons::Lit(v) => (*v, *v),
+            Cons::Var(_) => (1, 9),
+            Cons::Expr(expr) => expr.range,
+        }
+    }
+
+    fn as_expr(&self) -> Option<(Op, &Cons, &Cons)> {
+        match self {
+            Cons::Expr(expr) => Some((expr.op, &expr.left, &expr.right)),
+            _ => None,
+        }
+    }
+
+    /// Builds the expression `left op right`, simplified as far as the value
+    /// ranges of the operands allow.
+    fn new_expr(op: Op, left: Cons, right: Cons) -> Cons {
+        use Cons::*;
+        use Op::*;
+
+        if let (Lit(l), Lit(r)) = (&left, &right) {
+            // undefined operations are kept as they are
+            if let Ok(v) = apply(op, *l, *r) {
+                return Lit(v);
             }
-            if pos == 1 && allowed.contains(&(ch + 4)) {
-                digits[pos] = ch;
-                digits[14] = ch + 4;
+        }
+
+        let (lr, rr) = (left.range(), right.range());
+        match (op, &left, &right) {
+            // identities
+            (Add, l, Lit(0)) | (Mul, l, Lit(1)) | (Div, l, Lit(1)) => return l.clone(),
+            (Add, Lit(0), r) | (Mul, Lit(1), r) => return r.clone(),
+            (Mul, Lit(0), _) | (Mul, _, Lit(0)) => return Lit(0),
+            // remainder of a smaller non-negative number is the number itself
+            (Rem, l, _) if lr.0 >= 0 && lr.1 < rr.0 => return l.clone(),
+            // eql can't be true if the ranges don't intersect
+            (Eql, _, _) if lr.1 < rr.0 || rr.1 < lr.0 => return Lit(0),
+            (Eql, l, r) if l == r => return Lit(1),
+            // (e + a) + b = e + (a + b)
+            (Add, l, Lit(b)) => {
+                if let Some((Add, e, Lit(a))) = l.as_expr() {
+                    if let Some(ab) = a.checked_add(*b) {
+                        return Cons::new_expr(Add, e.clone(), Lit(ab));
+                    }
+                }
             }
-            if pos == 2 && allowed.contains(&(ch + 6)) {
-                digits[pos] = ch;
-                digits[13] = ch + 6;
+            // (hi * c + lo) / c = hi, and (hi * c + lo) % c = lo, if 0 <= lo < c
+            (Div | Rem, l, Lit(c)) => {
+                if let Some((Add, prod, lo)) = l.as_expr() {
+                    if let Some((Mul, hi, Lit(k))) = prod.as_expr() {
+                        let (lo_range, hi_range) = (lo.range(), hi.range());
+                        if k == c
+                            && hi_range.0 >= 0
+                            && lo_range.0 >= 0
+                            && lo_range.1 < *c
+                        {
+                            return if op == Div { hi.clone() } else { lo.clone() };
+                        }
+                    }
+                }
             }
-            if pos == 3 && allowed.contains(&(ch - 6)) {
-                digits[pos] = ch;
-                digits[12] = ch - 6;
+            _ => {}
+        }
+
+        match op_range(op, lr, rr) {
+            (lo, hi) if lo == hi => Lit(lo),
+            range => Expr(Rc::new(self::Expr {
+                op,
+                left,
+                right,
+                range,
+            })),
+        }
+    }
+}
+
+/// Bounds the result of the operation, given the bounds of its operands.
+fn op_range(op: Op, (a0, a1): Range, (b0, b1): Range) -> Range {
+    let corners = |f: fn(i128, i128) -> i128| {
+        let vals = [(a0, b0), (a0, b1), (a1, b0), (a1, b1)].map(|(a, b)| {
+            f(a as i128, b as i128).clamp(i64::MIN as i128, i64::MAX as i128)
+        });
+        let (lo, hi) = vals.iter().minmax().into_option().unwrap();
+        (*lo as i64, *hi as i64)
+    };
+    match op {
+        Op::Add => (a0.saturating_add(b0), a1.saturating_add(b1)),
+        Op::Mul => corners(|a, b| a * b),
+        // the divisor doesn't change sign, so the quotient is monotonic in both
+        Op::Div if b0 > 0 || b1 < 0 => corners(|a, b| a / b),
+        Op::Div => {
+            let m = a0.saturating_abs().max(a1.saturating_abs());
+            (-m, m)
+        }
+        Op::Rem => (0, a1.min(b1.saturating_sub(1)).max(0)),
+        Op::Eql => (0, 1),
+    }
+}
+
+impl fmt::Display for Cons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cons::Lit(a) => write!(f, "{}", a),
+            Cons::Var(n) => write!(f, "d{}", n),
+            Cons::Expr(expr) => {
+                write!(f, "({} {} {})", expr.op, expr.left, expr.right)
             }
-            if pos == 4 && allowed.contains(&(ch - 1)) {
-                digits[pos] = ch;
-                digits[9] = ch - 1;
+        }
+    }
+}
+
+/// ALU that runs the program on unknown digits, and keeps the simplified
+/// expressions for the registers.
+#[derive(Debug)]
+struct SymbolicAlu {
+    registers: [Cons; 4],
+    inputs: usize,
+}
+
+impl fmt::Display for SymbolicAlu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ALU: w={}  x={}  y={}  z={}",
+            self.get(Reg::W),
+            self.get(Reg::X),
+            self.get(Reg::Y),
+            self.get(Reg::Z)
+        )
+    }
+}
+
+impl SymbolicAlu {
+    fn new() -> Self {
+        Self {
+            registers: [Cons::Lit(0), Cons::Lit(0), Cons::Lit(0), Cons::Lit(0)],
+            inputs: 0,
+        }
+    }
+
+    fn get(&self, reg: Reg) -> &Cons {
+        &self.registers[reg as usize]
+    }
+
+    fn value(&self, val: Operand) -> Cons {
+        match val {
+            Operand::Reg(reg) => self.get(reg).clone(),
+            Operand::Lit(v) => Cons::Lit(v),
+        }
+    }
+
+    fn execute(&mut self, instr: Instr) {
+        let (reg, res) = match instr {
+            Instr::Inp(reg) => {
+                self.inputs += 1;
+                (reg, Cons::Var(self.inputs))
             }
-            if pos == 5 && allowed.contains(&ch) {
-                digits[pos] = ch;
-                digits[6] = ch;
+            Instr::Bin(op, reg, val) => (
+                reg,
+                Cons::new_expr(op, self.get(reg).clone(), self.value(val)),
+            ),
+        };
+        self.registers[reg as usize] = res;
+    }
+
+    fn run(&mut self, program: &[Instr]) {
+        program.iter().for_each(|&instr| self.execute(instr));
+    }
+}
+
+/// Arithmetic logic unit, which runs the program on the queue of inputs.
+#[derive(Debug)]
+struct Alu {
+    registers: [i64; 4],
+    inputs: VecDeque<i64>,
+}
+
+impl fmt::Display for Alu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ALU: w={}  x={}  y={}  z={}",
+            self.get(Reg::W),
+            self.get(Reg::X),
+            self.get(Reg::Y),
+            self.get(Reg::Z)
+        )
+    }
+}
+
+impl Alu {
+    fn new(inputs: impl IntoIterator<Item = i64>) -> Self {
+        Self {
+            registers: [0; 4],
+            inputs: inputs.into_iter().collect(),
+        }
+    }
+
+    fn get(&self, reg: Reg) -> i64 {
+        self.registers[reg as usize]
+    }
+
+    fn value(&self, val: Operand) -> i64 {
+        match val {
+            Operand::Reg(reg) => self.get(reg),
+            Operand::Lit(v) => v,
+        }
+    }
+
+    fn execute(&mut self, instr: Instr) -> Result<(), aoc::Error> {
+        let (reg, res) = match instr {
+            Instr::Inp(reg) => match self.inputs.pop_front() {
+                Some(v) => (reg, v),
+                None => return make_err!("no input left"),
+            },
+            Instr::Bin(op, reg, val) => {
+                (reg, apply(op, self.get(reg), self.value(val))?)
             }
-            if pos == 7 && allowed.contains(&(ch - 4)) {
-                digits[pos] = ch;
-                digits[8] = ch - 4;
+        };
+        self.registers[reg as usize] = res;
+        Ok(())
+    }
+
+    fn run(&mut self, program: &[Instr]) -> Result<(), aoc::Error> {
+        for (k, &instr) in program.iter().enumerate() {
+            self.execute(instr).map_err(|err| {
+                err.context(format!("Instruction {} `{}`", k + 1, instr))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the program on the digits of the model number, and checks that it's valid.
+fn verify(program: &[Instr], model: usize) -> Result<(), aoc::Error> {
+    let digits = model
+        .to_string()
+        .bytes()
+        .map(|b| (b - b'0') as i64)
+        .collect_vec();
+    let mut alu = Alu::new(digits);
+    alu.run(program)?;
+    match alu.get(Reg::Z) {
+        0 => Ok(()),
+        z => make_err!("Model number {} is invalid, z = {}", model, z),
+    }
+}
+
+/// Every digit of the model number is checked by the block of instructions below.
+/// Blocks differ only in the literals marked with `_`.
+const BLOCK_TEMPLATE: [&str; 18] = [
+    "inp w", "mul x 0", "add x z", "mod x 26", "div z _", "add x _", "eql x w",
+    "eql x 0", "mul y 0", "add y 25", "mul y x", "add y 1", "mul z y", "mul y 0",
+    "add y w", "add y _", "mul y x", "add z y",
+];
+
+const DIGITS: usize = 14;
+
+/// Parameters of the MONAD block for one digit.
+///
+/// The block treats `z` as a stack of base-26 numbers. If `div` is 1, it pushes
+/// `digit + offset`. If `div` is 26, it pops the top value, and pushes nothing
+/// only if `top + check == digit`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Block {
+    div: i64,
+    check: i64,
+    offset: i64,
+}
+
+/// Constraint `digits[right] == digits[left] + diff` on the model number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Constraint {
+    left: usize,
+    right: usize,
+    diff: i64,
+}
+
+fn parse_blocks(program: &[Instr]) -> Result<Vec<Block>, aoc::Error> {
+    if program.len() != DIGITS * BLOCK_TEMPLATE.len() {
+        return make_err!(
+            "Expected {} blocks of {} instructions, found {} instructions",
+            DIGITS,
+            BLOCK_TEMPLATE.len(),
+            program.len()
+        );
+    }
+
+    program
+        .chunks(BLOCK_TEMPLATE.len())
+        .enumerate()
+        .map(|(b, block)| {
+            let mut params = vec![];
+            for (k, (instr, pattern)) in block.iter().zip(BLOCK_TEMPLATE).enumerate() {
+                let text = instr.to_string();
+                let matched = match pattern.strip_suffix('_') {
+                    Some(prefix) => match text.strip_prefix(prefix) {
+                        Some(val) => val.parse().map(|v| params.push(v)).is_ok(),
+                        None => false,
+                    },
+                    None => text == pattern,
+                };
+                if !matched {
+                    return make_err!(
+                        "Instruction {}: expected `{}`, found `{}`",
+                        b * BLOCK_TEMPLATE.len() + k + 1,
+                        pattern,
+                        text
+                    );
+                }
+            }
+            Ok(Block {
+                div: params[0],
+                check: params[1],
+                offset: params[2],
+            })
+        })
+        .collect()
+}
+
+/// Pairs up the pushing and popping blocks, and derives the constraints
+/// under which every popping block leaves `z` unchanged.
+fn derive_constraints(blocks: &[Block]) -> Result<Vec<Constraint>, aoc::Error> {
+    let mut stack = vec![];
+    let mut constraints = vec![];
+
+    for (pos, block) in blocks.iter().enumerate() {
+        match block.div {
+            // digits are 1..=9, so the pushing block can't match them
+            1 if block.check > 9 => stack.push((pos, block.offset)),
+            1 => {
+                return make_err!(
+                    "Block {} pushes, but its check {} can match a digit",
+                    pos + 1,
+                    block.check
+                );
             }
-            if pos == 10 && allowed.contains(&(ch + 7)) {
-                digits[pos] = ch;
-                digits[11] = ch + 7;
+            26 => match stack.pop() {
+                Some((left, offset)) => constraints.push(Constraint {
+                    left,
+                    right: pos,
+                    diff: offset + block.check,
+                }),
+                None => {
+                    return make_err!("Block {} pops from the empty stack", pos + 1);
+                }
+            },
+            div => {
+                return make_err!("Block {} divides z by {}", pos + 1, div);
             }
         }
     }
 
-    digits
-        .into_iter()
-        .rev()
-        .zip(0..)
-        .map(|(d, p)| d as usize * 10_usize.pow(p))
-        .sum()
+    if !stack.is_empty() {
+        return make_err!("Blocks push more values than they pop");
+    }
+    Ok(constraints)
+}
+
+/// Finds the largest or the smallest model number that satisfies the constraints.
+fn get_model(constraints: &[Constraint], largest: bool) -> Result<usize, aoc::Error> {
+    let mut digits = [0; DIGITS];
+
+    for &Constraint { left, right, diff } in constraints {
+        let d = if largest {
+            9.min(9 - diff)
+        } else {
+            1.max(1 - diff)
+        };
+        if !(1..=9).contains(&d) || !(1..=9).contains(&(d + diff)) {
+            return make_err!(
+                "No digits satisfy d{} + {} = d{}",
+                left + 1,
+                diff,
+                right + 1
+            );
+        }
+        digits[left] = d;
+        digits[right] = d + diff;
+    }
+
+    Ok(digits.iter().fold(0, |acc, &d| acc * 10 + d as usize))
 }
 
 struct Solver;
 
+impl Solver {
+    fn parse_input(&self, input: Input) -> (Vec<Instr>, Vec<Constraint>) {
+        let program: Vec<Instr> = input.parse_lines().unwrap();
+        let blocks = parse_blocks(&program).unwrap_or_else(|err| {
+            // show what the unfamiliar program computes instead
+            let mut alu = SymbolicAlu::new();
+            alu.run(&program);
+            panic!("{}\nSimplified z = {}", err, alu.get(Reg::Z))
+        });
+        let constraints = derive_constraints(&blocks).unwrap();
+        (program, constraints)
+    }
+}
+
 impl Solution for Solver {
     type Output = usize;
 
-    fn solve_a(&self, _input: Lines) -> usize {
-        get_model((1..=9).rev())
+    fn solve_a(&self, input: Input) -> usize {
+        let (program, constraints) = self.parse_input(input);
+        let model = get_model(&constraints, true).unwrap();
+        verify(&program, model).unwrap();
+        model
     }
 
-    fn solve_b(&self, _input: Lines) -> usize {
-        get_model(1..=9)
+    fn solve_b(&self, input: Input) -> usize {
+        let (program, constraints) = self.parse_input(input);
+        let model = get_model(&constraints, false).unwrap();
+        verify(&program, model).unwrap();
+        model
     }
 }
 
@@ -240,10 +611,43 @@ mod tests {
     use super::*;
     use adventofcode::StripMargin;
 
-    #[test]
-    fn test_solution() {
-        let solver = Solver {};
-        let input = r"
+    /// Builds the MONAD program from the (div, check, offset) parameters of its blocks.
+    fn monad(params: &[(i64, i64, i64)]) -> String {
+        params
+            .iter()
+            .flat_map(|&(div, check, offset)| {
+                let mut vals = [div, check, offset].into_iter();
+                BLOCK_TEMPLATE.iter().map(move |pattern| {
+                    match pattern.strip_suffix('_') {
+                        Some(prefix) => format!("{}{}", prefix, vals.next().unwrap()),
+                        None => pattern.to_string(),
+                    }
+                })
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    const PARAMS: [(i64, i64, i64); DIGITS] = [
+        (1, 15, 13),
+        (1, 10, 16),
+        (1, 12, 2),
+        (1, 10, 8),
+        (1, 14, 11),
+        (26, -11, 6),
+        (1, 11, 12),
+        (26, -16, 2),
+        (26, -9, 2),
+        (1, 13, 3),
+        (26, 4, 1),
+        (26, -8, 5),
+        (26, -10, 7),
+        (26, -9, 9),
+    ];
+
+    /// MONAD program from the puzzle input.
+    fn example() -> String {
+        r"
             |inp w
             |mul x 0
             |add x z
@@ -498,12 +902,234 @@ mod tests {
             |add z y
         "
         .trim()
-        .strip_margin();
+        .strip_margin()
+    }
+
+    #[test]
+    fn test_solution() {
+        let solver = Solver {};
+        let input = example();
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 53_999_995_829_399);
 
-        let ans = solver.solve_b(input.lines());
+        let ans = solver.solve_b(Input::new(&input));
         assert_eq!(ans, 11_721_151_118_175);
     }
+
+    #[test]
+    fn test_constraints() {
+        let program: Vec<Instr> = Input::new(&example()).parse_lines().unwrap();
+        let blocks = parse_blocks(&program).unwrap();
+        let constraints = derive_constraints(&blocks).unwrap();
+
+        assert_eq!(constraints.len(), 7);
+        assert!(constraints.contains(&Constraint {
+            left: 0,
+            right: 13,
+            diff: 4
+        }));
+        assert!(constraints.contains(&Constraint {
+            left: 4,
+            right: 5,
+            diff: 0
+        }));
+    }
+
+    #[test]
+    fn test_invalid_programs() {
+        let err = Input::new("inp w\nadd x 1\nsub x 2")
+            .parse_lines::<Instr>()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: line 3: Unknown instruction: sub (in \"sub x 2\")"
+        );
+
+        let program: Vec<Instr> =
+            Input::new(&monad(&PARAMS[..2])).parse_lines().unwrap();
+        assert!(parse_blocks(&program).is_err());
+
+        let mut params = PARAMS;
+        params[5].0 = 1;
+        let program: Vec<Instr> = Input::new(&monad(&params)).parse_lines().unwrap();
+        let blocks = parse_blocks(&program).unwrap();
+        assert_eq!(
+            derive_constraints(&blocks).unwrap_err().to_string(),
+            "Error: Block 6 pushes, but its check -11 can match a digit"
+        );
+
+        let mut params = PARAMS;
+        params[13].1 = 9;
+        let program: Vec<Instr> = Input::new(&monad(&params)).parse_lines().unwrap();
+        let constraints = derive_constraints(&parse_blocks(&program).unwrap()).unwrap();
+        assert_eq!(
+            get_model(&constraints, true).unwrap_err().to_string(),
+            "Error: No digits satisfy d1 + 22 = d14"
+        );
+    }
+
+    fn run(program: &str, inputs: &[i64]) -> Result<Alu, aoc::Error> {
+        let program: Vec<Instr> = Input::new(program).parse_lines()?;
+        let mut alu = Alu::new(inputs.iter().copied());
+        alu.run(&program)?;
+        Ok(alu)
+    }
+
+    #[test]
+    fn test_alu() {
+        let alu = run("inp x\nmul x -1", &[7]).unwrap();
+        assert_eq!(alu.get(Reg::X), -7);
+
+        let three_times = "inp z\ninp x\nmul z 3\neql z x";
+        assert_eq!(run(three_times, &[3, 9]).unwrap().get(Reg::Z), 1);
+        assert_eq!(run(three_times, &[3, 8]).unwrap().get(Reg::Z), 0);
+
+        let binary = r"
+            |inp w
+            |add z w
+            |mod z 2
+            |div w 2
+            |add y w
+            |mod y 2
+            |div w 2
+            |add x w
+            |mod x 2
+            |div w 2
+            |mod w 2
+        "
+        .trim()
+        .strip_margin();
+        let alu = run(&binary, &[13]).unwrap();
+        assert_eq!(alu.to_string(), "ALU: w=1  x=1  y=0  z=1");
+
+        assert_eq!(run("inp w\ndiv w -2", &[-7]).unwrap().get(Reg::W), 3);
+    }
+
+    #[test]
+    fn test_alu_errors() {
+        let err = run("inp w\ndiv w x", &[1]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Instruction 2 `div w x`: division by zero"
+        );
+
+        let err = run("inp w\nmod w 3", &[-1]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Instruction 2 `mod w 3`: modulo of -1 by 3 is undefined"
+        );
+
+        let err = run("inp w\nmod w 0", &[1]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Instruction 2 `mod w 0`: modulo of 1 by 0 is undefined"
+        );
+
+        let err = run("inp w\ninp x", &[1]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Instruction 2 `inp x`: no input left"
+        );
+
+        let err = run("inp w\nmul w w\nmul w w", &[1 << 20]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Instruction 3 `mul w w`: overflow in 1099511627776 * 1099511627776"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let program: Vec<Instr> = Input::new(&example()).parse_lines().unwrap();
+
+        assert!(verify(&program, 53_999_995_829_399).is_ok());
+        assert!(verify(&program, 11_721_151_118_175).is_ok());
+        assert_eq!(
+            verify(&program, 53_999_995_829_398)
+                .unwrap_err()
+                .to_string(),
+            "Error: Model number 53999995829398 is invalid, z = 18"
+        );
+    }
+
+    /// Evaluates the expression for the given digits.
+    fn eval(cons: &Cons, digits: &[i64]) -> i64 {
+        match cons {
+            Cons::Lit(v) => *v,
+            Cons::Var(n) => digits[n - 1],
+            Cons::Expr(expr) => {
+                let (a, b) = (eval(&expr.left, digits), eval(&expr.right, digits));
+                apply(expr.op, a, b).unwrap()
+            }
+        }
+    }
+
+    fn symbolic(program: &str) -> SymbolicAlu {
+        let program: Vec<Instr> = Input::new(program).parse_lines().unwrap();
+        let mut alu = SymbolicAlu::new();
+        alu.run(&program);
+        alu
+    }
+
+    #[test]
+    fn test_simplification() {
+        use Cons::*;
+
+        assert_eq!(Cons::new_expr(Op::Mul, Var(1), Lit(0)), Lit(0));
+        assert_eq!(Cons::new_expr(Op::Rem, Var(1), Lit(10)), Var(1));
+        assert_eq!(Cons::new_expr(Op::Div, Var(1), Lit(10)), Lit(0));
+        assert_eq!(Cons::new_expr(Op::Eql, Var(1), Lit(10)), Lit(0));
+        assert_eq!(Cons::new_expr(Op::Eql, Var(2), Var(2)), Lit(1));
+        // undefined operations aren't folded
+        let expr = Cons::new_expr(Op::Div, Lit(1), Lit(0));
+        assert_eq!(expr.to_string(), "(/ 1 0)");
+
+        let sum = Cons::new_expr(Op::Add, Var(1), Var(2));
+        assert_eq!(sum.range(), (2, 18));
+        let prod = Cons::new_expr(Op::Mul, sum, Lit(-3));
+        assert_eq!(prod.range(), (-54, -6));
+        assert_eq!(Cons::new_expr(Op::Eql, prod, Lit(0)), Lit(0));
+
+        let alu = symbolic("inp w\nadd x w\nadd x 12\nadd x -2\nmod x 26\neql x w");
+        assert_eq!(alu.to_string(), "ALU: w=d1  x=0  y=0  z=0");
+    }
+
+    #[test]
+    fn test_symbolic_monad() {
+        // pushing blocks only stack up the digits
+        let alu = symbolic(&monad(&PARAMS[..2]));
+        assert_eq!(
+            alu.get(Reg::Z).to_string(),
+            "(+ (* (+ d1 13) 26) (+ d2 16))"
+        );
+
+        // popping block checks the digit against the top of the stack
+        let alu = symbolic(&monad(&[(1, 12, 2), (26, -5, 1)]));
+        assert_eq!(
+            alu.get(Reg::Z).to_string(),
+            "(* (+ d2 1) (= (= (+ d1 -3) d2) 0))"
+        );
+
+        let program: Vec<Instr> = Input::new(&example()).parse_lines().unwrap();
+        let mut alu = SymbolicAlu::new();
+        alu.run(&program);
+        let z = alu.get(Reg::Z);
+        assert_eq!(z.range().0, 0);
+
+        for model in [
+            53_999_995_829_399_u64,
+            11_721_151_118_175,
+            53_999_995_829_398,
+        ] {
+            let digits = model
+                .to_string()
+                .bytes()
+                .map(|b| (b - b'0') as i64)
+                .collect_vec();
+            let mut concrete = Alu::new(digits.iter().copied());
+            concrete.run(&program).unwrap();
+            assert_eq!(eval(z, &digits), concrete.get(Reg::Z));
+        }
+    }
 }
@@ -1,20 +1,22 @@
-use adventofcode::{self as aoc, aoc_problem, set, Solution};
-use std::str::Lines;
-
-struct Solver;
-
-impl Solution for Solver {
-    type Output = usize;
-
-    fn solve_a(&self, input: Lines) -> usize {
-        let mut grid: Vec<Vec<_>> = input
-            .map(|line| {
-                line.chars()
-                    .map(|c| if c == '.' { None } else { Some(c) })
-                    .collect()
-            })
-            .collect();
+use adventofcode::{
+    self as aoc, aoc_problem, set, util::simulation::Simulation, Input, Solution,
+};
+use std::collections::HashSet;
+
+type Grid = Vec<Vec<Option<char>>>;
+
+/// Herds of sea cucumbers, along with the positions of those that can move
+/// and the number of those that moved during the last step.
+#[derive(Clone)]
+struct Herds {
+    grid: Grid,
+    east_queue: HashSet<(usize, usize)>,
+    south_queue: HashSet<(usize, usize)>,
+    last_moved: usize,
+}
 
+impl Herds {
+    fn new(grid: Grid) -> Self {
         let n = grid.len();
         let m = grid[0].len();
 
@@ -23,73 +25,112 @@ impl Solution for Solver {
         for i in 0..n {
             for j in 0..m {
                 if let Some(c) = &grid[i][j] {
-                    if *c == '>' && grid[i][(j + 1) % m] == None {
+                    if *c == '>' && grid[i][(j + 1) % m].is_none() {
                         east_queue.insert((i, j));
                     }
-                    if *c == 'v' && grid[(i + 1) % n][j] == None {
+                    if *c == 'v' && grid[(i + 1) % n][j].is_none() {
                         south_queue.insert((i, j));
                     }
                 }
             }
         }
 
-        for step in 1.. {
-            if south_queue.is_empty() && east_queue.is_empty() {
-                return step;
-            }
+        Self {
+            grid,
+            east_queue,
+            south_queue,
+            last_moved: 0,
+        }
+    }
+}
 
-            let mut east_new = set![];
-            let mut south_new = set![];
+impl Simulation for Herds {
+    type State = Grid;
 
-            for &(i, j) in &east_queue {
-                if grid[i][(j + 1) % m] == None {
-                    grid[i][(j + 1) % m] = Some('>');
-                    grid[i][j] = None;
-                }
+    fn step(&mut self) {
+        let Self {
+            grid,
+            east_queue,
+            south_queue,
+            last_moved,
+        } = self;
+        let n = grid.len();
+        let m = grid[0].len();
+
+        *last_moved = 0;
+        let mut east_new = set![];
+        let mut south_new = set![];
+
+        for &(i, j) in east_queue.iter() {
+            if grid[i][(j + 1) % m].is_none() {
+                grid[i][(j + 1) % m] = Some('>');
+                grid[i][j] = None;
+                *last_moved += 1;
             }
-            for &(i, j) in &east_queue {
-                if grid[i][j] == None {
-                    south_queue.remove(&((i + n - 1) % n, (j + 1) % m));
-                    if grid[i][(j + 2) % m] == None {
-                        east_new.insert((i, (j + 1) % m));
-                    }
-                    if grid[i][(j + m - 1) % m] == Some('>') {
-                        east_new.insert((i, (j + m - 1) % m));
-                    }
-                    if grid[(i + n - 1) % n][j] == Some('v') {
-                        south_queue.insert(((i + n - 1) % n, j));
-                    }
+        }
+        for &(i, j) in east_queue.iter() {
+            if grid[i][j].is_none() {
+                south_queue.remove(&((i + n - 1) % n, (j + 1) % m));
+                if grid[i][(j + 2) % m].is_none() {
+                    east_new.insert((i, (j + 1) % m));
+                }
+                if grid[i][(j + m - 1) % m] == Some('>') {
+                    east_new.insert((i, (j + m - 1) % m));
+                }
+                if grid[(i + n - 1) % n][j] == Some('v') {
+                    south_queue.insert(((i + n - 1) % n, j));
                 }
             }
-            east_queue = east_new;
+        }
+        *east_queue = east_new;
 
-            for &(i, j) in &south_queue {
-                if grid[(i + 1) % n][j] == None {
-                    grid[(i + 1) % n][j] = Some('v');
-                    grid[i][j] = None;
-                }
+        for &(i, j) in south_queue.iter() {
+            if grid[(i + 1) % n][j].is_none() {
+                grid[(i + 1) % n][j] = Some('v');
+                grid[i][j] = None;
+                *last_moved += 1;
             }
-            for &(i, j) in &south_queue {
-                if grid[i][j] == None {
-                    east_queue.remove(&((i + 1) % n, (j + m - 1) % m));
-                    if grid[(i + 2) % n][j] == None {
-                        south_new.insert(((i + 1) % n, j));
-                    }
-                    if grid[(i + n - 1) % n][j] == Some('v') {
-                        south_new.insert(((i + n - 1) % n, j));
-                    }
-                    if grid[i][(j + m - 1) % m] == Some('>') {
-                        east_queue.insert((i, (j + m - 1) % m));
-                    }
+        }
+        for &(i, j) in south_queue.iter() {
+            if grid[i][j].is_none() {
+                east_queue.remove(&((i + 1) % n, (j + m - 1) % m));
+                if grid[(i + 2) % n][j].is_none() {
+                    south_new.insert(((i + 1) % n, j));
+                }
+                if grid[(i + n - 1) % n][j] == Some('v') {
+                    south_new.insert(((i + n - 1) % n, j));
+                }
+                if grid[i][(j + m - 1) % m] == Some('>') {
+                    east_queue.insert((i, (j + m - 1) % m));
                 }
             }
-            south_queue = south_new;
         }
+        *south_queue = south_new;
+    }
+
+    fn state(&self) -> Grid {
+        self.grid.clone()
+    }
+}
+
+struct Solver;
+
+impl Solution for Solver {
+    type Output = usize;
+
+    fn solve_a(&self, input: Input) -> usize {
+        let grid: Grid = input
+            .map(|line| {
+                line.chars()
+                    .map(|c| if c == '.' { None } else { Some(c) })
+                    .collect()
+            })
+            .collect();
 
-        unreachable!()
+        Herds::new(grid).run_until(|herds| herds.last_moved == 0)
     }
 
-    fn solve_b(&self, _input: Lines) -> usize {
+    fn solve_b(&self, _input: Input) -> usize {
         unreachable!("Merry X-mas!")
     }
 }
@@ -127,7 +168,7 @@ mod tests {
         .trim()
         .strip_margin_of("| ");
 
-        let ans = solver.solve_a(input.lines());
+        let ans = solver.solve_a(Input::new(&input));
         assert_eq!(ans, 58);
     }
 }
@@ -2,6 +2,8 @@ pub mod util;
 
 // re-exports
 pub use util::client::{earn_star, Client};
+pub use util::collections::{Counter, DefaultMap};
 pub use util::error::Error;
+pub use util::input::Input;
 pub use util::problem::{Problem, Solution};
 pub use util::strip_margin::StripMargin;
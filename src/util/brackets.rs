@@ -0,0 +1,299 @@
+//! Checker for lines of nested chunks, delimited by pairs of brackets.
+use crate::{make_err, Error};
+use std::fmt;
+
+/// Pairs of opening and closing characters of the chunks.
+///
+/// Characters that don't belong to any pair are ignored.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::brackets::{Brackets, Status};
+///
+/// let brackets = Brackets::new(&[('(', ')'), ('<', '>')]).unwrap();
+///
+/// assert_eq!(brackets.check("(<>)"), Status::Good);
+/// assert_eq!(brackets.check("(<"), Status::Incomplete(">)".to_string()));
+///
+/// let err = brackets.check("(<))");
+/// assert_eq!(err.to_string(), "column 3: expected `>`, found `)`");
+/// assert_eq!(brackets.repair("(<))").unwrap().apply("(<))"), "(<>)");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Brackets {
+    pairs: Vec<(char, char)>,
+}
+
+/// Result of checking a line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Status {
+    /// All the chunks are closed properly.
+    Good,
+    /// Some chunks are never closed; holds the characters that close them.
+    Incomplete(String),
+    /// Some chunk is closed with the wrong character.
+    Corrupted(Corruption),
+}
+
+/// Closing character that doesn't match the chunk it closes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Corruption {
+    /// 1-based column of the character.
+    pub column: usize,
+    pub found: char,
+    /// Character that closes the innermost open chunk, if there is one.
+    pub expected: Option<char>,
+}
+
+/// Change of one character in a line.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fix {
+    Replace {
+        column: usize,
+        found: char,
+        with: char,
+    },
+    Insert {
+        column: usize,
+        with: char,
+    },
+    Delete {
+        column: usize,
+        found: char,
+    },
+}
+
+impl Brackets {
+    /// Creates the checker for the given pairs of opening and closing characters.
+    ///
+    /// Returns `Err` if some character is used more than once.
+    pub fn new(pairs: &[(char, char)]) -> Result<Self, Error> {
+        let mut seen = vec![];
+        for &(open, close) in pairs {
+            for c in [open, close] {
+                if seen.contains(&c) {
+                    return make_err!("`{}` is used by more than one pair", c);
+                }
+                seen.push(c);
+            }
+        }
+        Ok(Self {
+            pairs: pairs.to_vec(),
+        })
+    }
+
+    /// Returns the character that closes the chunk opened with `open`.
+    pub fn closing(&self, open: char) -> Option<char> {
+        self.pairs.iter().find(|p| p.0 == open).map(|p| p.1)
+    }
+
+    fn is_closing(&self, c: char) -> bool {
+        self.pairs.iter().any(|p| p.1 == c)
+    }
+
+    /// Checks that every chunk of the line is closed with the right character.
+    pub fn check(&self, line: &str) -> Status {
+        let mut expected = vec![];
+        for (col, c) in line.chars().enumerate() {
+            if let Some(close) = self.closing(c) {
+                expected.push(close);
+            } else if self.is_closing(c) {
+                match expected.pop() {
+                    Some(close) if close == c => {}
+                    close => {
+                        return Status::Corrupted(Corruption {
+                            column: col + 1,
+                            found: c,
+                            expected: close,
+                        })
+                    }
+                }
+            }
+        }
+
+        if expected.is_empty() {
+            Status::Good
+        } else {
+            Status::Incomplete(expected.iter().rev().collect())
+        }
+    }
+
+    /// Finds the change of one character that stops the line from being corrupted.
+    ///
+    /// The changes closest to the corrupted character come first. At every column,
+    /// replacing a character is preferred to deleting it, which is preferred
+    /// to inserting one.
+    /// Returns `None` if the line isn't corrupted, or if one change isn't enough.
+    pub fn repair(&self, line: &str) -> Option<Fix> {
+        let column = match self.check(line) {
+            Status::Corrupted(corruption) => corruption.column,
+            _ => return None,
+        };
+
+        let chars: Vec<char> = line.chars().collect();
+        let closing: Vec<char> = self.pairs.iter().map(|p| p.1).collect();
+        let opening: Vec<char> = self.pairs.iter().map(|p| p.0).collect();
+
+        // the prefix before the corrupted character is fine,
+        // so the change must happen at or before it
+        (1..=column).rev().find_map(|col| {
+            let found = chars[col - 1];
+            let replace = |with: &[char]| {
+                with.iter()
+                    .filter(|&&with| with != found)
+                    .map(|&with| Fix::Replace {
+                        column: col,
+                        found,
+                        with,
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let insert = |with: &[char]| {
+                with.iter()
+                    .map(|&with| Fix::Insert { column: col, with })
+                    .collect::<Vec<_>>()
+            };
+
+            // prefer closing the chunks to opening the new ones
+            replace(&closing)
+                .into_iter()
+                .chain([Fix::Delete { column: col, found }])
+                .chain(insert(&closing))
+                .chain(replace(&opening))
+                .chain(insert(&opening))
+                .find(|fix| {
+                    !matches!(self.check(&fix.apply(line)), Status::Corrupted(_))
+                })
+        })
+    }
+}
+
+/// Chunks of the navigation subsystem: `()`, `[]`, `{}` and `<>`.
+impl Default for Brackets {
+    fn default() -> Self {
+        Self::new(&[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')]).unwrap()
+    }
+}
+
+impl Fix {
+    /// Returns the line with the change applied.
+    pub fn apply(&self, line: &str) -> String {
+        let mut chars: Vec<char> = line.chars().collect();
+        match *self {
+            Fix::Replace { column, with, .. } => chars[column - 1] = with,
+            Fix::Insert { column, with } => chars.insert(column - 1, with),
+            Fix::Delete { column, .. } => {
+                chars.remove(column - 1);
+            }
+        }
+        chars.into_iter().collect()
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Good => write!(f, "good"),
+            Status::Incomplete(rest) => {
+                write!(f, "incomplete, complete by adding `{}`", rest)
+            }
+            Status::Corrupted(corruption) => write!(f, "{}", corruption),
+        }
+    }
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expected {
+            Some(expected) => write!(
+                f,
+                "column {}: expected `{}`, found `{}`",
+                self.column, expected, self.found
+            ),
+            None => write!(
+                f,
+                "column {}: found `{}`, but no chunk is open",
+                self.column, self.found
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::Replace {
+                column,
+                found,
+                with,
+            } => write!(
+                f,
+                "replace `{}` with `{}` at column {}",
+                found, with, column
+            ),
+            Fix::Insert { column, with } => {
+                write!(f, "insert `{}` at column {}", with, column)
+            }
+            Fix::Delete { column, found } => {
+                write!(f, "delete `{}` at column {}", found, column)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let brackets = Brackets::default();
+        assert_eq!(brackets.check("a(b[c]d)e"), Status::Good);
+        assert_eq!(
+            brackets.check("[({(<(())[]>[[{[]{<()<>>"),
+            Status::Incomplete("}}]])})]".to_string())
+        );
+        assert_eq!(
+            brackets.check("{([(<{}[<>[]}>{[]{[(<()>"),
+            Status::Corrupted(Corruption {
+                column: 13,
+                found: '}',
+                expected: Some(']'),
+            })
+        );
+        assert_eq!(
+            brackets.check("()>").to_string(),
+            "column 3: found `>`, but no chunk is open"
+        );
+
+        let err = Brackets::new(&[('(', ')'), ('[', '(')]).unwrap_err();
+        assert_eq!(err.to_string(), "Error: `(` is used by more than one pair");
+        let err = Brackets::new(&[('|', '|')]).unwrap_err();
+        assert_eq!(err.to_string(), "Error: `|` is used by more than one pair");
+    }
+
+    #[test]
+    fn test_repair() {
+        let brackets = Brackets::default();
+        for (line, fix, fixed) in [
+            (
+                "{([(<{}[<>[]}>{[]{[(<()>",
+                "replace `}` with `]` at column 13",
+                "{([(<{}[<>[]]>{[]{[(<()>",
+            ),
+            ("(])", "delete `]` at column 2", "()"),
+            ("()>", "delete `>` at column 3", "()"),
+            ("<(]>", "replace `]` with `)` at column 3", "<()>"),
+            ("[(<)]", "insert `>` at column 4", "[(<>)]"),
+        ] {
+            let actual = brackets.repair(line).unwrap();
+            assert_eq!(actual.to_string(), fix);
+            assert_eq!(actual.apply(line), fixed);
+        }
+
+        // one change isn't enough
+        assert_eq!(brackets.repair("(((]]]"), None);
+        assert_eq!(brackets.repair("(()"), None);
+        assert_eq!(brackets.repair("}}}"), None);
+    }
+}
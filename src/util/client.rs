@@ -1,7 +1,7 @@
 use crate::{
     make_err,
     util::problem::{Part, Problem},
-    Solution,
+    Input, Solution,
 };
 use std::{env, fmt, fs, path, result};
 
@@ -60,7 +60,7 @@ impl Client {
                 .call()?
                 .into_string()?;
 
-            fs::create_dir_all(&pth.parent().unwrap())?;
+            fs::create_dir_all(pth.parent().unwrap())?;
             fs::write(&pth, resp)?;
         }
 
@@ -108,9 +108,16 @@ pub fn earn_star<T: Solution>(problem: Problem, solver: T) -> ClientResult<Strin
             make_err!("Can't solve a problem if no part is specified :(")
         }
         Some(part) => {
+            let input = Input::new(&input);
+            if env::var_os("AOC_EXPLAIN").is_some() {
+                if let Some(explanation) = solver.explain(part, input.clone()) {
+                    println!("{}", explanation);
+                }
+            }
+
             let ans = match part {
-                Part::A => solver.solve_a(input.lines()),
-                Part::B => solver.solve_b(input.lines()),
+                Part::A => solver.solve_a(input),
+                Part::B => solver.solve_b(input),
             };
             println!("{}", &ans);
 
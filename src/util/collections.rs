@@ -1,3 +1,7 @@
+use std::collections::{hash_map, HashMap};
+use std::hash::Hash;
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Sub};
+
 /// Macro to construct a HashSet from given elements.
 ///
 /// # Example
@@ -48,9 +52,337 @@ macro_rules! map {
     }
 }
 
+/// Macro to construct a Counter from given elements, or from element-count pairs.
+///
+/// # Example
+/// ```
+/// # use adventofcode::counter;
+///
+/// let c = counter!['a', 'b', 'a'];
+/// assert_eq!(c[&'a'], 2);
+/// assert_eq!(c[&'z'], 0);
+///
+/// let c = counter!['a' => 2, 'b' => 1];
+/// assert_eq!(c.total(), 3);
+/// ```
+#[macro_export]
+macro_rules! counter {
+    () => {{ $crate::util::collections::Counter::new() }};
+    ($($k:expr => $n:expr),+ $(,)?) => {
+        {
+            let mut c = $crate::util::collections::Counter::new();
+            $( $crate::util::collections::Counter::add_n(&mut c, $k, $n); )+
+            c
+        }
+    };
+    ($($elem:expr),+ $(,)?) => {
+        {
+            let mut c = $crate::util::collections::Counter::new();
+            $( $crate::util::collections::Counter::add(&mut c, $elem); )+
+            c
+        }
+    };
+}
+
+/// A multiset, which keeps track of how many times each element was added.
+///
+/// Missing elements have count zero, and elements never have count zero
+/// inside the counter. Subtraction saturates, dropping elements whose count
+/// falls to zero.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::collections::Counter;
+///
+/// let c: Counter<char> = "NNCB".chars().collect();
+///
+/// assert_eq!(c[&'N'], 2);
+/// assert_eq!(c.max_by_count(), Some((&'N', 2)));
+/// assert_eq!(c.most_common()[0], ('N', 2));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Counter<K: Eq + Hash> {
+    counts: HashMap<K, usize>,
+}
+
+impl<K: Eq + Hash> Counter<K> {
+    /// Creates an empty counter.
+    pub fn new() -> Self {
+        Self {
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Adds one occurrence of `key`.
+    pub fn add(&mut self, key: K) {
+        self.add_n(key, 1);
+    }
+
+    /// Adds `n` occurrences of `key`.
+    pub fn add_n(&mut self, key: K, n: usize) {
+        if n > 0 {
+            *self.counts.entry(key).or_insert(0) += n;
+        }
+    }
+
+    /// Removes up to `n` occurrences of `key`, returning how many were removed.
+    pub fn remove_n(&mut self, key: &K, n: usize) -> usize {
+        match self.counts.get_mut(key) {
+            Some(cnt) if *cnt > n => {
+                *cnt -= n;
+                n
+            }
+            Some(_) => self.counts.remove(key).unwrap(),
+            None => 0,
+        }
+    }
+
+    /// Returns how many times `key` was added.
+    pub fn get(&self, key: &K) -> usize {
+        self.counts.get(key).copied().unwrap_or(0)
+    }
+
+    /// Adds all the occurrences from the other counter.
+    pub fn merge(&mut self, other: Self) {
+        for (k, n) in other.counts {
+            self.add_n(k, n);
+        }
+    }
+
+    /// Returns the number of distinct elements.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns `true` if nothing was added to the counter.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Returns the total number of occurrences of all elements.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// Iterates over the distinct elements and their counts, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, usize)> {
+        self.counts.iter().map(|(k, &n)| (k, n))
+    }
+
+    /// Iterates over the distinct elements, in arbitrary order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.counts.keys()
+    }
+
+    /// Returns the element with the lowest count, if any.
+    pub fn min_by_count(&self) -> Option<(&K, usize)> {
+        self.iter().min_by_key(|&(_, n)| n)
+    }
+
+    /// Returns the element with the highest count, if any.
+    pub fn max_by_count(&self) -> Option<(&K, usize)> {
+        self.iter().max_by_key(|&(_, n)| n)
+    }
+
+    /// Returns multiplied counts of all the elements, e.g., when every
+    /// occurrence stands for `factor` parallel universes.
+    pub fn scaled(self, factor: usize) -> Self {
+        if factor == 0 {
+            return Self::new();
+        }
+        Self {
+            counts: self
+                .counts
+                .into_iter()
+                .map(|(k, n)| (k, n * factor))
+                .collect(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash + Ord> Counter<K> {
+    /// Returns all the elements with their counts, from the most common
+    /// to the least common. Ties are ordered by the element.
+    pub fn most_common(&self) -> Vec<(K, usize)> {
+        let mut v: Vec<_> = self.iter().map(|(k, n)| (k.clone(), n)).collect();
+        v.sort_by(|(k1, n1), (k2, n2)| n2.cmp(n1).then_with(|| k1.cmp(k2)));
+        v
+    }
+}
+
+impl<K: Eq + Hash> Default for Counter<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash> Index<&K> for Counter<K> {
+    type Output = usize;
+
+    fn index(&self, key: &K) -> &usize {
+        self.counts.get(key).unwrap_or(&0)
+    }
+}
+
+impl<K: Eq + Hash> FromIterator<K> for Counter<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut c = Self::new();
+        c.extend(iter);
+        c
+    }
+}
+
+impl<K: Eq + Hash> Extend<K> for Counter<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|k| self.add(k));
+    }
+}
+
+impl<K: Eq + Hash> IntoIterator for Counter<K> {
+    type Item = (K, usize);
+    type IntoIter = hash_map::IntoIter<K, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.counts.into_iter()
+    }
+}
+
+impl<K: Eq + Hash> Add for Counter<K> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self.merge(rhs);
+        self
+    }
+}
+
+impl<K: Eq + Hash> AddAssign for Counter<K> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.merge(rhs);
+    }
+}
+
+impl<K: Eq + Hash> Sub for Counter<K> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self {
+        for (k, n) in rhs.counts {
+            self.remove_n(&k, n);
+        }
+        self
+    }
+}
+
+impl<K: Eq + Hash> Mul<usize> for Counter<K> {
+    type Output = Self;
+
+    fn mul(self, rhs: usize) -> Self {
+        self.scaled(rhs)
+    }
+}
+
+/// A HashMap that returns a default value for missing keys.
+///
+/// Mutable indexing inserts a copy of the default value first,
+/// so the map can be updated in place without `entry().or_insert()`.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::collections::DefaultMap;
+///
+/// let mut m = DefaultMap::new(vec![]);
+/// m[&"a"].push(1);
+/// m[&"a"].push(2);
+///
+/// assert_eq!(m[&"a"], vec![1, 2]);
+/// assert!(m[&"b"].is_empty());
+/// assert_eq!(m.len(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DefaultMap<K: Eq + Hash, V> {
+    default: V,
+    map: HashMap<K, V>,
+}
+
+impl<K: Eq + Hash, V> DefaultMap<K, V> {
+    /// Creates an empty map, with the given value for missing keys.
+    pub fn new(default: V) -> Self {
+        Self {
+            default,
+            map: HashMap::new(),
+        }
+    }
+
+    /// Returns the value for `key`, or the default value if the key is missing.
+    pub fn get(&self, key: &K) -> &V {
+        self.map.get(key).unwrap_or(&self.default)
+    }
+
+    /// Inserts a value for `key`, returning the previous one, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    /// Removes `key` from the map, returning the previous value, if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    /// Returns `true` if the map has an explicit value for `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Returns the number of keys with explicit values.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if no key has an explicit value.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterates over the keys with explicit values, in arbitrary order.
+    pub fn iter(&self) -> hash_map::Iter<'_, K, V> {
+        self.map.iter()
+    }
+
+    /// Converts into the underlying HashMap, dropping the default value.
+    pub fn into_inner(self) -> HashMap<K, V> {
+        self.map
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> DefaultMap<K, V> {
+    /// Returns a mutable reference to the value for `key`,
+    /// inserting the default value if the key is missing.
+    pub fn get_mut(&mut self, key: &K) -> &mut V {
+        if !self.map.contains_key(key) {
+            self.map.insert(key.clone(), self.default.clone());
+        }
+        self.map.get_mut(key).unwrap()
+    }
+}
+
+impl<K: Eq + Hash, V> Index<&K> for DefaultMap<K, V> {
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        self.get(key)
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> IndexMut<&K> for DefaultMap<K, V> {
+    fn index_mut(&mut self, key: &K) -> &mut V {
+        self.get_mut(key)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::{HashMap, HashSet};
+    use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_set() {
@@ -84,4 +416,58 @@ mod tests {
             assert_eq!(&m[&1], "qux");
         }
     }
+
+    #[test]
+    fn test_counter() {
+        let mut c: Counter<char> = "NBCCNBBBCBHCB".chars().collect();
+        assert_eq!(c.len(), 4);
+        assert_eq!(c.total(), 13);
+        assert_eq!(c.max_by_count(), Some((&'B', 6)));
+        assert_eq!(c.min_by_count(), Some((&'H', 1)));
+        assert_eq!(
+            c.most_common(),
+            vec![('B', 6), ('C', 4), ('N', 2), ('H', 1)]
+        );
+
+        assert_eq!(c.remove_n(&'H', 5), 1);
+        assert_eq!(c[&'H'], 0);
+        assert_eq!(c.len(), 3);
+
+        c.add_n('X', 0);
+        assert_eq!(c.len(), 3);
+        assert!(Counter::<u8>::new().max_by_count().is_none());
+    }
+
+    #[test]
+    fn test_counter_arithmetic() {
+        let a = counter!['a' => 3, 'b' => 1];
+        let b = counter!['a', 'b', 'b', 'c'];
+
+        assert_eq!(
+            a.clone() + b.clone(),
+            counter!['a' => 4, 'b' => 3, 'c' => 1]
+        );
+        assert_eq!(a.clone() - b.clone(), counter!['a' => 2]);
+        assert_eq!(b - a.clone(), counter!['b' => 1, 'c' => 1]);
+        assert_eq!(a.clone() * 3, counter!['a' => 9, 'b' => 3]);
+        assert!(a.scaled(0).is_empty());
+
+        let mut c: Counter<char> = counter![];
+        c += counter!['z'];
+        c.merge(counter!['z' => 2]);
+        assert_eq!(c[&'z'], 3);
+    }
+
+    #[test]
+    fn test_default_map() {
+        let mut m = DefaultMap::new(0);
+        m[&(1, 2)] += 5;
+        m[&(1, 2)] += 1;
+
+        assert_eq!(m[&(1, 2)], 6);
+        assert_eq!(m[&(0, 0)], 0);
+        assert!(!m.contains_key(&(0, 0)));
+        assert_eq!(m.remove(&(1, 2)), Some(6));
+        assert!(m.is_empty());
+    }
 }
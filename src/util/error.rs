@@ -35,6 +35,21 @@ impl Error {
     pub fn new(msg: impl Into<String>) -> Self {
         Self { msg: msg.into() }
     }
+
+    /// Prepends the context to the error message.
+    ///
+    /// # Example
+    /// ```
+    /// use adventofcode::Error;
+    ///
+    /// let err = Error::new("division by zero").context("Instruction 7");
+    /// assert_eq!(err.to_string(), "Error: Instruction 7: division by zero");
+    /// ```
+    pub fn context(self, ctx: impl fmt::Display) -> Self {
+        Self {
+            msg: format!("{}: {}", ctx, self.msg),
+        }
+    }
 }
 
 impl fmt::Display for Error {
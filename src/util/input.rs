@@ -0,0 +1,367 @@
+//! Puzzle input passed to the solvers.
+use crate::Error;
+use std::fmt::Display;
+use std::str::{FromStr, Lines};
+
+/// Puzzle input, split into lines, blank-line separated sections, or a grid.
+///
+/// `Input` is itself an iterator over the remaining lines of the input,
+/// so solvers written against `Lines` keep working after switching to `Input`.
+/// Every line remembers its 1-based position in the original text, which is used
+/// to give context to the parsing errors.
+///
+/// # Example
+/// ```
+/// use adventofcode::Input;
+///
+/// let input = Input::new("1,2,3\n\nfold along y=-7\nfold along x=5\n");
+/// let sections: Vec<_> = input.sections().collect();
+///
+/// assert_eq!(sections.len(), 2);
+/// assert_eq!(sections[0].numbers::<u32>().unwrap(), vec![1, 2, 3]);
+/// assert_eq!(sections[1].numbers::<i32>().unwrap(), vec![-7, 5]);
+/// assert_eq!(sections[1].line_no(), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Input<'a> {
+    text: &'a str,
+    line_no: usize,
+}
+
+impl<'a> Input<'a> {
+    /// Wraps the raw text of the puzzle input.
+    pub fn new(text: &'a str) -> Self {
+        Self { text, line_no: 1 }
+    }
+
+    /// Returns the remaining text of the input.
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    /// Returns the 1-based number of the first remaining line.
+    pub fn line_no(&self) -> usize {
+        self.line_no
+    }
+
+    /// Returns the remaining lines of the input.
+    pub fn lines(&self) -> Lines<'a> {
+        self.text.lines()
+    }
+
+    /// Returns the remaining lines of the input, along with their 1-based numbers.
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+        (self.line_no..).zip(self.text.lines())
+    }
+
+    /// Splits the input into sections separated by one or more blank lines.
+    ///
+    /// # Example
+    /// ```
+    /// use adventofcode::Input;
+    ///
+    /// let input = Input::new("a\nb\n\n\nc\n");
+    /// let sections: Vec<Vec<_>> = input.sections().map(|s| s.collect()).collect();
+    ///
+    /// assert_eq!(sections, vec![vec!["a", "b"], vec!["c"]]);
+    /// ```
+    pub fn sections(&self) -> Sections<'a> {
+        Sections { rest: self.clone() }
+    }
+
+    /// Parses the input as a rectangular grid of characters.
+    pub fn grid(&self) -> Vec<Vec<char>> {
+        self.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    /// Parses the input as a grid, converting every character with `f`.
+    ///
+    /// Returns `Err` pointing to the line and column of the first character
+    /// that can't be converted.
+    ///
+    /// # Example
+    /// ```
+    /// use adventofcode::Input;
+    ///
+    /// let input = Input::new("12\n3x\n");
+    /// let err = input.try_grid(|c| c.to_digit(10)).unwrap_err();
+    ///
+    /// assert_eq!(err.to_string(), "Error: line 2, column 2: unexpected 'x'");
+    /// ```
+    pub fn try_grid<T>(
+        &self,
+        f: impl Fn(char) -> Option<T>,
+    ) -> Result<Vec<Vec<T>>, Error> {
+        self.numbered_lines()
+            .map(|(no, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(col, c)| match f(c) {
+                        Some(v) => Ok(v),
+                        None => Err(Error::new(format!(
+                            "line {}, column {}: unexpected {:?}",
+                            no,
+                            col + 1,
+                            c
+                        ))),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Extracts all (possibly negative) integers from the input.
+    ///
+    /// # Example
+    /// ```
+    /// use adventofcode::Input;
+    ///
+    /// let input = Input::new("target area: x=20..30, y=-10..-5");
+    /// assert_eq!(input.numbers::<i32>().unwrap(), vec![20, 30, -10, -5]);
+    /// ```
+    pub fn numbers<T>(&self) -> Result<Vec<T>, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let mut nums = vec![];
+        for (no, line) in self.numbered_lines() {
+            for token in integer_tokens(line) {
+                nums.push(parse_with_context(no, token)?);
+            }
+        }
+        Ok(nums)
+    }
+
+    /// Parses every line of the input via `FromStr`.
+    ///
+    /// Returns `Err` with the line number and the offending line on failure.
+    ///
+    /// # Example
+    /// ```
+    /// use adventofcode::Input;
+    ///
+    /// let input = Input::new("199\n200\nfoo\n");
+    /// let err = input.parse_lines::<u32>().unwrap_err();
+    ///
+    /// assert!(err.to_string().starts_with("Error: line 3: "));
+    /// ```
+    pub fn parse_lines<T>(&self) -> Result<Vec<T>, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.try_map_lines(|line| line.parse::<T>())
+    }
+
+    /// Converts every line of the input with `f`, adding the line number
+    /// and the offending line to the error on failure.
+    pub fn try_map_lines<T, E: Display>(
+        &self,
+        mut f: impl FnMut(&'a str) -> Result<T, E>,
+    ) -> Result<Vec<T>, Error> {
+        self.numbered_lines()
+            .map(|(no, line)| f(line).map_err(|err| line_error(no, err, line)))
+            .collect()
+    }
+}
+
+fn integer_tokens(line: &str) -> impl Iterator<Item = &str> {
+    let bytes = line.as_bytes();
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while pos < bytes.len() {
+            let start = pos;
+            let signed = bytes[pos] == b'-'
+                && bytes.get(pos + 1).map_or(false, u8::is_ascii_digit);
+            if signed || bytes[pos].is_ascii_digit() {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+                return Some(&line[start..pos]);
+            }
+            pos += 1;
+        }
+        None
+    })
+}
+
+fn parse_with_context<T>(no: usize, token: &str) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    token.parse().map_err(|err| line_error(no, err, token))
+}
+
+fn line_error(no: usize, err: impl Display, text: &str) -> Error {
+    // don't repeat the prefix when the cause is an `aoc::Error` itself
+    let msg = err.to_string();
+    let msg = msg.strip_prefix("Error: ").unwrap_or(&msg);
+    Error::new(format!("line {}: {} (in {:?})", no, msg, text))
+}
+
+impl<'a> From<&'a str> for Input<'a> {
+    fn from(text: &'a str) -> Self {
+        Self::new(text)
+    }
+}
+
+/// Existing solvers that operate on `Lines` can convert the input back.
+impl<'a> From<Input<'a>> for Lines<'a> {
+    fn from(input: Input<'a>) -> Self {
+        input.lines()
+    }
+}
+
+impl<'a> Iterator for Input<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.text.is_empty() {
+            return None;
+        }
+        let (line, rest) = match self.text.split_once('\n') {
+            Some((line, rest)) => (line, rest),
+            None => (self.text, ""),
+        };
+        self.text = rest;
+        self.line_no += 1;
+        Some(line.strip_suffix('\r').unwrap_or(line))
+    }
+}
+
+/// Iterator over the blank-line separated sections of `Input`.
+#[derive(Clone, Debug)]
+pub struct Sections<'a> {
+    rest: Input<'a>,
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = Input<'a>;
+
+    fn next(&mut self) -> Option<Input<'a>> {
+        let mut input = self.rest.clone();
+
+        // skip the blank lines before the section
+        loop {
+            let mut peek = input.clone();
+            match peek.next() {
+                Some(line) if line.trim().is_empty() => input = peek,
+                Some(_) => break,
+                None => {
+                    self.rest = peek;
+                    return None;
+                }
+            }
+        }
+
+        let start = input.clone();
+        let mut len = 0;
+        loop {
+            let mut peek = input.clone();
+            match peek.next() {
+                Some(line) if !line.trim().is_empty() => {
+                    len += input.text.len() - peek.text.len();
+                    input = peek;
+                }
+                _ => break,
+            }
+        }
+        self.rest = input;
+
+        Some(Input {
+            text: &start.text[..len],
+            line_no: start.line_no,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StripMargin;
+
+    #[test]
+    fn test_lines() {
+        let text = "a\r\nb\n\nc";
+        let input = Input::new(text);
+        assert_eq!(input.clone().collect::<Vec<_>>(), vec!["a", "b", "", "c"]);
+        assert_eq!(input.lines().collect::<Vec<_>>(), vec!["a", "b", "", "c"]);
+
+        let lines: Lines = input.into();
+        assert_eq!(lines.count(), 4);
+    }
+
+    #[test]
+    fn test_sections() {
+        let text = r"
+            |7,4,9
+            |
+            |22 13
+            | 8  2
+            |
+            |
+            | 3 15
+            |"
+        .strip_margin();
+        let input = Input::new(&text);
+
+        let sections: Vec<_> = input.sections().collect();
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].as_str(), "7,4,9\n");
+        assert_eq!(
+            sections[1].lines().collect::<Vec<_>>(),
+            vec!["22 13", " 8  2"]
+        );
+        assert_eq!(sections[2].line_no(), 8);
+        assert_eq!(sections[2].numbers::<u32>().unwrap(), vec![3, 15]);
+    }
+
+    #[test]
+    fn test_sections_of_partially_consumed_input() {
+        let mut input = Input::new("header\nx\n\ny\n");
+        assert_eq!(input.next(), Some("header"));
+
+        let sections: Vec<_> = input.sections().map(|s| s.line_no()).collect();
+        assert_eq!(sections, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_numbers() {
+        let input = Input::new("on x=-20..26,y=-36..17\nfold along y=7\n3-4");
+        assert_eq!(
+            input.numbers::<i64>().unwrap(),
+            vec![-20, 26, -36, 17, 7, 3, -4]
+        );
+
+        let err = input.numbers::<u8>().unwrap_err();
+        assert!(err.to_string().starts_with("Error: line 1: "));
+    }
+
+    #[test]
+    fn test_grid() {
+        let input = Input::new("#.\n.#\n");
+        assert_eq!(input.grid(), vec![vec!['#', '.'], vec!['.', '#']]);
+        assert_eq!(
+            input
+                .try_grid(|c| (c == '#').then(|| 1))
+                .unwrap_err()
+                .to_string(),
+            "Error: line 1, column 2: unexpected '.'"
+        );
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let input = Input::new("1\n2\n3");
+        assert_eq!(input.parse_lines::<u32>().unwrap(), vec![1, 2, 3]);
+
+        let err = Input::new("1\n-2").parse_lines::<u32>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: line 2: invalid digit found in string (in \"-2\")"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Integer matrices with fast exponentiation, to jump ahead in linear recurrences.
+use crate::{make_err, Error};
+use std::ops::{Index, IndexMut, Mul};
+
+/// Matrix of non-negative integers, with overflow-checked arithmetic.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::linalg::Matrix;
+///
+/// let fib = Matrix::from_rows(vec![vec![1, 1], vec![1, 0]]);
+///
+/// assert_eq!(fib.checked_pow(90).unwrap()[(0, 1)], 2_880_067_194_370_816_120);
+/// assert!(fib.checked_pow(100).is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<u64>,
+}
+
+impl Matrix {
+    /// Creates a matrix filled with zeros.
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            data: vec![0; rows * cols],
+        }
+    }
+
+    /// Creates an identity matrix of size `n`.
+    pub fn identity(n: usize) -> Self {
+        let mut m = Self::zeros(n, n);
+        for i in 0..n {
+            m[(i, i)] = 1;
+        }
+        m
+    }
+
+    /// Creates a matrix from its rows, which should all have the same length.
+    pub fn from_rows(rows: Vec<Vec<u64>>) -> Self {
+        let cols = rows.first().map_or(0, Vec::len);
+        assert!(
+            rows.iter().all(|r| r.len() == cols),
+            "Rows differ in length"
+        );
+        Self {
+            rows: rows.len(),
+            cols,
+            data: rows.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Multiplies two matrices, or returns `Err` if any entry overflows.
+    pub fn checked_mul(&self, rhs: &Self) -> Result<Self, Error> {
+        assert_eq!(self.cols, rhs.rows, "Dimension mismatch");
+        let mut res = Self::zeros(self.rows, rhs.cols);
+        for i in 0..self.rows {
+            for k in 0..self.cols {
+                let a = self[(i, k)];
+                if a == 0 {
+                    continue;
+                }
+                for j in 0..rhs.cols {
+                    res[(i, j)] = a
+                        .checked_mul(rhs[(k, j)])
+                        .and_then(|prod| prod.checked_add(res[(i, j)]))
+                        .ok_or_else(overflow)?;
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Multiplies the matrix by a column vector, or returns `Err` on overflow.
+    pub fn checked_mul_vec(&self, v: &[u64]) -> Result<Vec<u64>, Error> {
+        let col = Self {
+            rows: v.len(),
+            cols: 1,
+            data: v.to_vec(),
+        };
+        self.checked_mul(&col).map(|res| res.data)
+    }
+
+    /// Raises a square matrix to the power `exp` by repeated squaring,
+    /// or returns `Err` on overflow.
+    pub fn checked_pow(&self, mut exp: u64) -> Result<Self, Error> {
+        assert_eq!(self.rows, self.cols, "Matrix is not square");
+        let mut res = Self::identity(self.rows);
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = res.checked_mul(&base)?;
+            }
+            exp >>= 1;
+            // don't square the base more than needed, since it could overflow
+            if exp > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+        Ok(res)
+    }
+}
+
+fn overflow() -> Error {
+    Error::new("Integer overflow in matrix multiplication")
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = u64;
+
+    fn index(&self, (i, j): (usize, usize)) -> &u64 {
+        assert!(i < self.rows && j < self.cols, "Index out of bounds");
+        &self.data[i * self.cols + j]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut u64 {
+        assert!(i < self.rows && j < self.cols, "Index out of bounds");
+        &mut self.data[i * self.cols + j]
+    }
+}
+
+/// Matrix of integers modulo `modulus`, which never overflows.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::linalg::{Matrix, ModMatrix};
+///
+/// let fib = Matrix::from_rows(vec![vec![1, 1], vec![1, 0]]);
+/// let fib = ModMatrix::new(fib, 1_000_000_007);
+///
+/// assert_eq!(fib.pow(1_000_000_000_000_000_000).matrix()[(0, 1)], 209_783_453);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModMatrix {
+    modulus: u64,
+    inner: Matrix,
+}
+
+impl ModMatrix {
+    /// Reduces all entries of the matrix modulo `modulus`.
+    pub fn new(mut inner: Matrix, modulus: u64) -> Self {
+        assert!(modulus > 0, "Modulus must be positive");
+        inner.data.iter_mut().for_each(|el| *el %= modulus);
+        Self { modulus, inner }
+    }
+
+    /// Returns the modulus.
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+    /// Returns the underlying matrix of the reduced entries.
+    pub fn matrix(&self) -> &Matrix {
+        &self.inner
+    }
+
+    /// Multiplies the matrix by a column vector, modulo `modulus`.
+    pub fn mul_vec(&self, v: &[u64]) -> Vec<u64> {
+        let col = Matrix {
+            rows: v.len(),
+            cols: 1,
+            data: v.to_vec(),
+        };
+        (self * &ModMatrix::new(col, self.modulus)).inner.data
+    }
+
+    /// Raises a square matrix to the power `exp` by repeated squaring.
+    pub fn pow(&self, mut exp: u64) -> Self {
+        assert_eq!(self.inner.rows, self.inner.cols, "Matrix is not square");
+        let mut res = Self::new(Matrix::identity(self.inner.rows), self.modulus);
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = &res * &base;
+            }
+            base = &base * &base;
+            exp >>= 1;
+        }
+        res
+    }
+}
+
+impl Mul for &ModMatrix {
+    type Output = ModMatrix;
+
+    fn mul(self, rhs: Self) -> ModMatrix {
+        assert_eq!(self.modulus, rhs.modulus, "Modulus mismatch");
+        assert_eq!(self.inner.cols, rhs.inner.rows, "Dimension mismatch");
+
+        let m = self.modulus as u128;
+        let mut res = Matrix::zeros(self.inner.rows, rhs.inner.cols);
+        for i in 0..self.inner.rows {
+            for j in 0..rhs.inner.cols {
+                let sum = (0..self.inner.cols)
+                    .map(|k| self.inner[(i, k)] as u128 * rhs.inner[(k, j)] as u128 % m)
+                    .fold(0, |acc, el| (acc + el) % m);
+                res[(i, j)] = sum as u64;
+            }
+        }
+        ModMatrix {
+            modulus: self.modulus,
+            inner: res,
+        }
+    }
+}
+
+/// Sums the entries of a vector, or returns `Err` on overflow.
+pub fn checked_sum(v: &[u64]) -> Result<u64, Error> {
+    match v.iter().try_fold(0_u64, |acc, &el| acc.checked_add(el)) {
+        Some(sum) => Ok(sum),
+        None => make_err!("Integer overflow in vector sum"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_mul() {
+        let a = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]);
+        let b = Matrix::from_rows(vec![vec![7, 8], vec![9, 10], vec![11, 12]]);
+
+        let c = a.checked_mul(&b).unwrap();
+        assert_eq!(c, Matrix::from_rows(vec![vec![58, 64], vec![139, 154]]));
+        assert_eq!(a.checked_mul_vec(&[1, 0, 1]).unwrap(), vec![4, 10]);
+
+        let big = Matrix::from_rows(vec![vec![u64::MAX / 2 + 1]]);
+        let err = big
+            .checked_mul(&Matrix::from_rows(vec![vec![2]]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Integer overflow in matrix multiplication"
+        );
+    }
+
+    #[test]
+    fn test_checked_pow() {
+        let m = Matrix::from_rows(vec![vec![2, 0], vec![0, 3]]);
+        assert_eq!(m.checked_pow(0).unwrap(), Matrix::identity(2));
+        assert_eq!(
+            m.checked_pow(10).unwrap(),
+            Matrix::from_rows(vec![vec![1024, 0], vec![0, 59049]])
+        );
+
+        // 2^63 fits, even though squaring the base once more would overflow
+        let two = Matrix::from_rows(vec![vec![2]]);
+        assert_eq!(two.checked_pow(63).unwrap()[(0, 0)], 1 << 63);
+        assert!(two.checked_pow(64).is_err());
+    }
+
+    #[test]
+    fn test_mod_matrix() {
+        let m = Matrix::from_rows(vec![vec![3, 1], vec![0, 2]]);
+        let exact = m.checked_pow(20).unwrap();
+        let modular = ModMatrix::new(m, 1000).pow(20);
+
+        for i in 0..2 {
+            for j in 0..2 {
+                assert_eq!(modular.matrix()[(i, j)], exact[(i, j)] % 1000);
+            }
+        }
+        assert_eq!(modular.mul_vec(&[1, 1]), vec![226, 576]);
+    }
+
+    #[test]
+    fn test_checked_sum() {
+        assert_eq!(checked_sum(&[1, 2, 3]).unwrap(), 6);
+        assert!(checked_sum(&[u64::MAX, 1]).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+//! Memoization for recursive solvers.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+/// Cache statistics of a memoized function.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub size: usize,
+}
+
+/// Cache of the results of a function, keyed on its (hashable) arguments.
+///
+/// The function itself is provided at the call site, and receives the cache back,
+/// so that it can make memoized recursive calls.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::memo::Memo;
+///
+/// fn fib(memo: &mut Memo<u64, u64>, n: u64) -> u64 {
+///     memo.cached(n, |memo| match n {
+///         0 | 1 => n,
+///         _ => fib(memo, n - 1) + fib(memo, n - 2),
+///     })
+/// }
+///
+/// let mut memo = Memo::new();
+/// assert_eq!(fib(&mut memo, 90), 2_880_067_194_370_816_120);
+/// assert_eq!(memo.stats().misses, 91);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Memo<A: Eq + Hash, R> {
+    cache: HashMap<A, R>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<A: Eq + Hash, R: Clone> Memo<A, R> {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the cached result for `args`, or computes it with `f` and caches it.
+    pub fn cached(&mut self, args: A, f: impl FnOnce(&mut Self) -> R) -> R {
+        if let Some(res) = self.cache.get(&args) {
+            self.hits += 1;
+            return res.clone();
+        }
+        self.misses += 1;
+        let res = f(self);
+        self.cache.insert(args, res.clone());
+        res
+    }
+
+    /// Returns the cached result for `args`, if any, without updating the stats.
+    pub fn get(&self, args: &A) -> Option<&R> {
+        self.cache.get(args)
+    }
+
+    /// Returns the number of cache hits and misses, and the size of the cache.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            size: self.cache.len(),
+        }
+    }
+
+    /// Drops all the cached results, and resets the stats.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.hits = 0;
+        self.misses = 0;
+    }
+}
+
+impl<A: Eq + Hash, R: Clone> Default for Memo<A, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type Body<'a, A, R> = dyn Fn(&mut Memoized<'a, A, R>, A) -> R + 'a;
+
+/// Wrapper that memoizes a recursive closure.
+///
+/// The closure receives the wrapper as its first argument, and makes
+/// recursive calls through it.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::memo::Memoized;
+///
+/// let coins = [1, 2, 5];
+/// // number of ways to pay `amount` with the first `n` coins
+/// let mut ways = Memoized::new(|ways, (amount, n): (usize, usize)| match (amount, n) {
+///     (0, _) => 1_u64,
+///     (_, 0) => 0,
+///     _ if coins[n - 1] > amount => ways.call((amount, n - 1)),
+///     _ => ways.call((amount, n - 1)) + ways.call((amount - coins[n - 1], n)),
+/// });
+///
+/// assert_eq!(ways.call((5, 3)), 4);
+/// assert_eq!(ways.call((1000, 3)), 50_401);
+///
+/// ways.clear();
+/// assert_eq!(ways.stats().size, 0);
+/// ```
+pub struct Memoized<'a, A: Eq + Hash, R> {
+    memo: Memo<A, R>,
+    body: Rc<Body<'a, A, R>>,
+}
+
+impl<'a, A: Clone + Eq + Hash, R: Clone> Memoized<'a, A, R> {
+    /// Wraps the closure `f` with an empty cache.
+    pub fn new(f: impl Fn(&mut Self, A) -> R + 'a) -> Self {
+        Self {
+            memo: Memo::new(),
+            body: Rc::new(f),
+        }
+    }
+
+    /// Calls the wrapped closure, or returns the cached result.
+    pub fn call(&mut self, args: A) -> R {
+        if let Some(res) = self.memo.cache.get(&args) {
+            self.memo.hits += 1;
+            return res.clone();
+        }
+        self.memo.misses += 1;
+        let body = Rc::clone(&self.body);
+        let res = body(self, args.clone());
+        self.memo.cache.insert(args, res.clone());
+        res
+    }
+
+    /// Returns the number of cache hits and misses, and the size of the cache.
+    pub fn stats(&self) -> CacheStats {
+        self.memo.stats()
+    }
+
+    /// Drops all the cached results, and resets the stats.
+    pub fn clear(&mut self) {
+        self.memo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collatz_len(memo: &mut Memo<u64, usize>, n: u64) -> usize {
+        memo.cached(n, |memo| match n {
+            1 => 1,
+            _ if n % 2 == 0 => 1 + collatz_len(memo, n / 2),
+            _ => 1 + collatz_len(memo, 3 * n + 1),
+        })
+    }
+
+    #[test]
+    fn test_memo() {
+        let mut memo = Memo::new();
+        assert_eq!(collatz_len(&mut memo, 27), 112);
+        assert_eq!(
+            memo.stats(),
+            CacheStats {
+                hits: 0,
+                misses: 112,
+                size: 112
+            }
+        );
+
+        // 82 is the second element of the sequence for 27
+        assert_eq!(collatz_len(&mut memo, 82), 111);
+        assert_eq!(memo.stats().hits, 1);
+        assert_eq!(memo.get(&82), Some(&111));
+
+        memo.clear();
+        assert_eq!(memo.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_memoized() {
+        let mut binom = Memoized::new(|binom, (n, k): (u64, u64)| {
+            if k == 0 || k == n {
+                1_u64
+            } else {
+                binom.call((n - 1, k - 1)) + binom.call((n - 1, k))
+            }
+        });
+
+        assert_eq!(binom.call((60, 30)), 118_264_581_564_861_424);
+        assert_eq!(binom.stats().size, binom.stats().misses);
+        assert!(binom.stats().hits > 0);
+    }
+}
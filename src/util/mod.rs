@@ -1,5 +1,13 @@
+pub mod brackets;
 pub mod client;
 pub mod collections;
 pub mod error;
+pub mod input;
+pub mod linalg;
+pub mod memo;
+pub mod pointcloud;
 pub mod problem;
+#[doc(hidden)]
+pub mod random;
+pub mod simulation;
 pub mod strip_margin;
@@ -0,0 +1,126 @@
+//! Export of 3D points to plain point-cloud formats, to inspect them in other tools.
+use std::fmt::Write;
+
+/// Named group of points, drawn in one color.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub color: [u8; 3],
+    pub points: Vec<[i64; 3]>,
+}
+
+/// Collection of points, grouped into layers.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::pointcloud::PointCloud;
+///
+/// let mut cloud = PointCloud::new();
+/// cloud.add_layer("origin", [255, 0, 0], [[0, 0, 0]]);
+/// cloud.add_layer("corners", [0, 0, 255], [[1, 2, 3], [-1, -2, -3]]);
+///
+/// assert_eq!(cloud.len(), 3);
+/// assert_eq!(cloud.to_csv(), "layer,x,y,z\norigin,0,0,0\ncorners,1,2,3\ncorners,-1,-2,-3\n");
+/// assert!(cloud.to_ply().starts_with("ply\nformat ascii 1.0\nelement vertex 3\n"));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PointCloud {
+    layers: Vec<Layer>,
+}
+
+impl PointCloud {
+    /// Creates an empty point cloud.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the group of points with the given name and color.
+    pub fn add_layer(
+        &mut self,
+        name: &str,
+        color: [u8; 3],
+        points: impl IntoIterator<Item = [i64; 3]>,
+    ) {
+        self.layers.push(Layer {
+            name: name.to_string(),
+            color,
+            points: points.into_iter().collect(),
+        });
+    }
+
+    /// Returns the layers in the order they were added.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Returns the total number of points.
+    pub fn len(&self) -> usize {
+        self.layers.iter().map(|l| l.points.len()).sum()
+    }
+
+    /// Returns `true` if the cloud has no points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the points as CSV, with the name of the layer in the first column.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("layer,x,y,z\n");
+        for layer in &self.layers {
+            for [x, y, z] in &layer.points {
+                writeln!(out, "{},{},{},{}", layer.name, x, y, z).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Writes the points in the ASCII PLY format, colored by their layers.
+    pub fn to_ply(&self) -> String {
+        let mut out = String::from("ply\nformat ascii 1.0\n");
+        writeln!(out, "element vertex {}", self.len()).unwrap();
+        for prop in [
+            "int x",
+            "int y",
+            "int z",
+            "uchar red",
+            "uchar green",
+            "uchar blue",
+        ] {
+            writeln!(out, "property {}", prop).unwrap();
+        }
+        out.push_str("end_header\n");
+
+        for layer in &self.layers {
+            let [r, g, b] = layer.color;
+            for [x, y, z] in &layer.points {
+                writeln!(out, "{} {} {} {} {} {}", x, y, z, r, g, b).unwrap();
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ply() {
+        let mut cloud = PointCloud::new();
+        assert!(cloud.is_empty());
+
+        cloud.add_layer("a", [1, 2, 3], vec![[10, -20, 30]]);
+        cloud.add_layer("b", [4, 5, 6], vec![]);
+        cloud.add_layer("c", [7, 8, 9], vec![[0, 0, 1], [0, 1, 0]]);
+
+        let ply = cloud.to_ply();
+        let lines: Vec<_> = ply.lines().collect();
+        assert_eq!(lines.len(), 10 + 3);
+        assert_eq!(lines[2], "element vertex 3");
+        assert_eq!(lines[9], "end_header");
+        assert_eq!(
+            &lines[10..],
+            ["10 -20 30 1 2 3", "0 0 1 7 8 9", "0 1 0 7 8 9"]
+        );
+    }
+}
@@ -1,5 +1,5 @@
+use crate::Input;
 use std::fmt::Display;
-use std::str::Lines;
 
 /// Advent of Code problems have parts A and B.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -49,9 +49,16 @@ impl Problem {
 pub trait Solution {
     type Output: Display + Sized;
     /// Compute answer for the Advent of Code problem (part A).
-    fn solve_a(&self, input: Lines) -> Self::Output;
+    fn solve_a(&self, input: Input) -> Self::Output;
     /// Compute answer for the Advent of Code problem (part B).
-    fn solve_b(&self, input: Lines) -> Self::Output;
+    fn solve_b(&self, input: Input) -> Self::Output;
+    /// Describe how the answer is found, e.g. step by step.
+    ///
+    /// `earn_star` prints the description if the `AOC_EXPLAIN` environment
+    /// variable is set. Most solvers have nothing to add.
+    fn explain(&self, _part: Part, _input: Input) -> Option<String> {
+        None
+    }
 }
 
 /// Macro to instantiate Problem for a given year and day.
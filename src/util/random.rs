@@ -0,0 +1,47 @@
+//! Seeded pseudo-random numbers, for generating test inputs.
+
+/// Linear congruential generator with Knuth's MMIX constants.
+///
+/// The same seed always gives the same sequence, so the generated inputs
+/// are reproducible. Not suitable for anything beyond that.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::random::Lcg;
+///
+/// let mut rng = Lcg::new(2021);
+/// let rolls: Vec<_> = (0..100).map(|_| rng.below(6) + 1).collect();
+///
+/// assert!(rolls.iter().all(|r| (1..=6).contains(r)));
+/// assert_eq!(rolls, Lcg::new(2021).take(100).map(|r| r % 6 + 1).collect::<Vec<_>>());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    /// Creates the generator from the seed.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns a number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "Range is empty");
+        self.next().unwrap() % n
+    }
+}
+
+/// Yields the high 31 bits of the state, which are the most random ones.
+impl Iterator for Lcg {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.state = self
+            .state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        Some(self.state >> 33)
+    }
+}
@@ -0,0 +1,234 @@
+//! Step-by-step simulations, with drivers to run them for a number of steps,
+//! until they stabilize, or far into the future via cycle detection.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Start and length of the cycle in the sequence of simulation states.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cycle {
+    /// Number of steps before the state enters the cycle.
+    pub start: usize,
+    /// Number of steps after which the states start repeating.
+    pub length: usize,
+}
+
+/// Trait implemented by puzzles that evolve one step at a time.
+///
+/// # Example
+/// ```
+/// use adventofcode::util::simulation::{Cycle, Simulation};
+///
+/// #[derive(Clone)]
+/// struct Squares(u64);
+///
+/// impl Simulation for Squares {
+///     type State = u64;
+///
+///     fn step(&mut self) {
+///         self.0 = (self.0 * self.0 + 1) % 255;
+///     }
+///
+///     fn state(&self) -> u64 {
+///         self.0
+///     }
+/// }
+///
+/// // 3, 10, 101, 2, 5, 26, 167, 95, 101, ...
+/// let cycle = Squares(3).find_cycle();
+/// assert_eq!(cycle, Cycle { start: 2, length: 6 });
+///
+/// let mut sim = Squares(3);
+/// sim.run_far(1_000_000_000_000);
+/// assert_eq!(sim.state(), 5);
+/// ```
+pub trait Simulation {
+    /// Snapshot of the simulation, which identifies repeated states.
+    type State: Eq + Hash;
+
+    /// Advances the simulation by one step.
+    fn step(&mut self);
+
+    /// Returns the snapshot of the current state.
+    fn state(&self) -> Self::State;
+
+    /// Returns the hash of the current state.
+    fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.state().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Runs the simulation for `steps` steps.
+    fn run(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    /// Runs the simulation until `done` returns `true`, and returns
+    /// the number of steps taken. The condition is checked after every step.
+    fn run_until(&mut self, mut done: impl FnMut(&Self) -> bool) -> usize {
+        let mut steps = 0;
+        loop {
+            self.step();
+            steps += 1;
+            if done(self) {
+                return steps;
+            }
+        }
+    }
+
+    /// Runs the simulation until a step no longer changes its state,
+    /// and returns the number of that step (1-based).
+    ///
+    /// Never returns if the simulation has no fixed point.
+    fn run_until_stable(&mut self) -> usize {
+        let mut prev = self.state();
+        self.run_until(|sim| {
+            let cur = sim.state();
+            let stable = cur == prev;
+            prev = cur;
+            stable
+        })
+    }
+
+    /// Finds the cycle in the sequence of states, starting from the current one,
+    /// using Brent's algorithm. The simulation itself is not advanced.
+    ///
+    /// Never returns if the states don't repeat.
+    fn find_cycle(&self) -> Cycle
+    where
+        Self: Clone,
+    {
+        // find the cycle length, by moving the tortoise to the hare
+        // each time the power of two is reached
+        let mut power = 1;
+        let mut length = 1;
+        let mut tortoise = self.state();
+        let mut hare = self.clone();
+        hare.step();
+        while hare.state() != tortoise {
+            if power == length {
+                tortoise = hare.state();
+                power *= 2;
+                length = 0;
+            }
+            hare.step();
+            length += 1;
+        }
+
+        // find the cycle start, by moving both with the same speed
+        // while the hare stays one cycle ahead
+        let mut tortoise = self.clone();
+        let mut hare = self.clone();
+        hare.run(length);
+        let mut start = 0;
+        while hare.state() != tortoise.state() {
+            tortoise.step();
+            hare.step();
+            start += 1;
+        }
+
+        Cycle { start, length }
+    }
+
+    /// Runs the simulation for `steps` steps, skipping the full
+    /// repetitions of the state cycle.
+    ///
+    /// Never returns if the states don't repeat.
+    fn run_far(&mut self, steps: u64)
+    where
+        Self: Clone,
+    {
+        let Cycle { start, length } = self.find_cycle();
+        let (start, length) = (start as u64, length as u64);
+
+        let remaining = if steps <= start {
+            steps
+        } else {
+            start + (steps - start) % length
+        };
+        self.run(remaining as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Walks along a rho-shaped path: 0, 1, ..., tail - 1, then loops
+    /// over tail, ..., tail + len - 1.
+    #[derive(Clone)]
+    struct Rho {
+        pos: usize,
+        tail: usize,
+        len: usize,
+        steps: usize,
+    }
+
+    impl Simulation for Rho {
+        type State = usize;
+
+        fn step(&mut self) {
+            self.pos += 1;
+            if self.pos == self.tail + self.len {
+                self.pos = self.tail;
+            }
+            self.steps += 1;
+        }
+
+        fn state(&self) -> usize {
+            self.pos
+        }
+    }
+
+    fn rho(tail: usize, len: usize) -> Rho {
+        Rho {
+            pos: 0,
+            tail,
+            len,
+            steps: 0,
+        }
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        for (tail, len) in [(0, 1), (0, 7), (1, 1), (5, 3), (13, 64), (100, 37)] {
+            let sim = rho(tail, len);
+            assert_eq!(
+                sim.find_cycle(),
+                Cycle {
+                    start: tail,
+                    length: len
+                }
+            );
+            assert_eq!(sim.steps, 0);
+        }
+    }
+
+    #[test]
+    fn test_run_far() {
+        let mut sim = rho(10, 7);
+        sim.run_far(1_000_000_000_000);
+        assert_eq!(sim.pos, 10 + (1_000_000_000_000 - 10) % 7);
+
+        let mut sim = rho(10, 7);
+        sim.run_far(4);
+        assert_eq!(sim.pos, 4);
+    }
+
+    #[test]
+    fn test_run_until() {
+        let mut sim = rho(3, 5);
+        assert_eq!(sim.run_until(|s| s.pos == 6), 6);
+        assert_eq!(sim.run_until(|s| s.pos == 6), 5);
+
+        // 0, 1, 2, 3, 3 - the fourth step is the first one without changes
+        let mut sim = rho(3, 1);
+        assert_eq!(sim.run_until_stable(), 4);
+
+        let mut other = rho(3, 1);
+        other.run(10);
+        assert_eq!(sim.state_hash(), other.state_hash());
+    }
+}